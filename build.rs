@@ -8,6 +8,7 @@
 fn main() {
     // prost_build::compile_protos(
     //     &[
+    //         "proto/names.proto",
     //         "proto/signed.proto",
     //         "proto/package.proto",
     //         "proto/versions.proto",
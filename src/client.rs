@@ -0,0 +1,717 @@
+//! High level clients that build, send, and parse requests in one call.
+//!
+//! The crate does not depend on any HTTP library. Instead requests are sent
+//! with a transport supplied by the user, implementing [`HttpClient`] or
+//! [`AsyncHttpClient`].
+//!
+//! ```
+//! use hexpm::client::{Client, HttpClient};
+//!
+//! struct Offline;
+//!
+//! impl HttpClient for Offline {
+//!     type Error = std::io::Error;
+//!
+//!     fn send(
+//!         &self,
+//!         _request: http::Request<Vec<u8>>,
+//!     ) -> Result<http::Response<Vec<u8>>, Self::Error> {
+//!         Err(std::io::ErrorKind::NotConnected.into())
+//!     }
+//! }
+//!
+//! let client = Client::new(Offline);
+//! assert!(client.get_names().is_err());
+//! ```
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    ApiError, ApiKey, AuditLog, Config, DeviceAuthorization, DeviceTokenPoll, KeyPermission,
+    OAuthToken, Organization, OrganizationMember, OrganizationRole, Owner, OwnerLevel, Package,
+    PackageInfo, PackageSearch, PackageSearchPage, PublishedRelease, RateLimit, RegistryVersions,
+    Release, ReleaseMeta, RetirementReason, User, UserPackage,
+};
+
+/// A blocking HTTP transport.
+pub trait HttpClient {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    fn send(&self, request: http::Request<Vec<u8>>)
+    -> Result<http::Response<Vec<u8>>, Self::Error>;
+}
+
+/// An asynchronous HTTP transport.
+pub trait AsyncHttpClient {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    fn send(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> impl Future<Output = Result<http::Response<Vec<u8>>, Self::Error>> + Send;
+}
+
+/// Define a client with a method for each request, generic over either the
+/// blocking or the asynchronous transport. The asynchronous client is given the
+/// `async` and `await` keywords, which are added to the methods that send
+/// requests.
+macro_rules! define_client {
+    (
+        $(#[$meta:meta])*
+        $client:ident<$transport:ident> $(, $async:ident $await:ident)?
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone)]
+        pub struct $client<T> {
+            pub transport: T,
+            pub config: Config,
+            /// The API key used to authenticate requests, if any.
+            pub api_key: Option<String>,
+            /// The PEM encoded public key used to verify repository resources.
+            /// Defaults to the key of the hex.pm repository.
+            pub repository_public_key: Vec<u8>,
+            /// The PEM encoded public keys of organization repositories, by
+            /// organization name. Organizations without a key use the
+            /// `repository_public_key`, as hex.pm signs the repositories of its
+            /// organizations with its own key.
+            pub organization_public_keys: HashMap<String, Vec<u8>>,
+            /// Shared between clones, as Hex limits requests by IP address and key.
+            rate_limit: Arc<Mutex<Option<RateLimit>>>,
+        }
+
+        impl<T: $transport> $client<T> {
+            pub fn new(transport: T) -> Self {
+                Self {
+                    transport,
+                    config: Config::new(),
+                    api_key: None,
+                    repository_public_key: crate::HEXPM_PUBLIC_KEY.to_vec(),
+                    organization_public_keys: HashMap::new(),
+                    rate_limit: Arc::default(),
+                }
+            }
+
+            /// The public key of the repository selected by the config.
+            fn public_key(&self) -> &[u8] {
+                self.public_key_for(&self.config)
+            }
+
+            /// The public key of the repository selected by the given config.
+            fn public_key_for(&self, config: &Config) -> &[u8] {
+                config
+                    .organization()
+                    .and_then(|organization| self.organization_public_keys.get(organization))
+                    .unwrap_or(&self.repository_public_key)
+            }
+
+            /// The rate limit of the Hex API, as reported by the most recent response
+            /// that included it.
+            pub fn rate_limit(&self) -> Option<RateLimit> {
+                *self.rate_limit.lock().expect("rate limit lock")
+            }
+
+            $($async)? fn send(
+                &self,
+                request: http::Request<Vec<u8>>,
+            ) -> Result<http::Response<Vec<u8>>, ApiError> {
+                let response = self
+                    .transport
+                    .send(request)
+                    $(.$await)?
+                    .map_err(|e| ApiError::Transport(Box::new(e)))?;
+                self.record_rate_limit(&response);
+                Ok(response)
+            }
+
+            fn record_rate_limit(&self, response: &http::Response<Vec<u8>>) {
+                if let Some(rate_limit) = RateLimit::from_headers(response.headers()) {
+                    *self.rate_limit.lock().expect("rate limit lock") = Some(rate_limit);
+                }
+            }
+
+            fn required_api_key(&self) -> Result<&str, ApiError> {
+                self.api_key.as_deref().ok_or(ApiError::MissingApiKey)
+            }
+
+            /// Create an API key, returning its secret. See
+            /// [`api_create_api_key_request`](crate::api_create_api_key_request).
+            pub $($async)? fn create_api_key(
+                &self,
+                username: &str,
+                password: &str,
+                key_name: &str,
+                otp: Option<&str>,
+            ) -> Result<String, ApiError> {
+                let request = crate::api_create_api_key_request(
+                    username,
+                    password,
+                    key_name,
+                    otp,
+                    &self.config,
+                );
+                crate::api_create_api_key_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn remove_api_key(
+                &self,
+                name_of_key_to_delete: &str,
+                otp: Option<&str>,
+            ) -> Result<(), ApiError> {
+                let request = crate::api_remove_api_key_request(
+                    name_of_key_to_delete,
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                );
+                crate::api_remove_api_key_response(self.send(request)$(.$await)??)
+            }
+
+            /// Create an API key with the given permissions, returning its secret.
+            pub $($async)? fn create_api_key_with_permissions(
+                &self,
+                username: &str,
+                password: &str,
+                key_name: &str,
+                permissions: &[KeyPermission],
+                otp: Option<&str>,
+            ) -> Result<String, ApiError> {
+                let request = crate::api_create_api_key_with_permissions_request(
+                    username,
+                    password,
+                    key_name,
+                    permissions,
+                    otp,
+                    &self.config,
+                );
+                crate::api_create_api_key_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn list_api_keys(&self) -> Result<Vec<ApiKey>, ApiError> {
+                let request =
+                    crate::api_list_api_keys_request(self.required_api_key()?, &self.config);
+                crate::api_list_api_keys_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn get_api_key(&self, name: &str) -> Result<ApiKey, ApiError> {
+                let request = crate::api_get_api_key_request(
+                    name,
+                    self.required_api_key()?,
+                    &self.config,
+                );
+                crate::api_get_api_key_response(self.send(request)$(.$await)??)
+            }
+
+            /// Remove all API keys of the user, including the key used by this client.
+            pub $($async)? fn remove_all_api_keys(
+                &self,
+                otp: Option<&str>,
+            ) -> Result<(), ApiError> {
+                let request = crate::api_remove_all_api_keys_request(
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                );
+                crate::api_remove_all_api_keys_response(self.send(request)$(.$await)??)
+            }
+
+            /// Start the OAuth device authorization flow. See
+            /// [`api_oauth_device_authorization_request`](crate::api_oauth_device_authorization_request).
+            pub $($async)? fn oauth_device_authorization(
+                &self,
+                client_id: &str,
+                scope: &str,
+            ) -> Result<DeviceAuthorization, ApiError> {
+                let request = crate::api_oauth_device_authorization_request(
+                    client_id,
+                    scope,
+                    &self.config,
+                );
+                crate::api_oauth_device_authorization_response(self.send(request)$(.$await)??)
+            }
+
+            /// Poll for the token of an OAuth device authorization.
+            pub $($async)? fn poll_oauth_device_token(
+                &self,
+                client_id: &str,
+                device_code: &str,
+            ) -> Result<DeviceTokenPoll, ApiError> {
+                let request =
+                    crate::api_oauth_device_token_request(client_id, device_code, &self.config);
+                crate::api_oauth_device_token_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn refresh_oauth_token(
+                &self,
+                client_id: &str,
+                refresh_token: &str,
+            ) -> Result<OAuthToken, ApiError> {
+                let request = crate::api_oauth_refresh_token_request(
+                    client_id,
+                    refresh_token,
+                    &self.config,
+                );
+                crate::api_oauth_refresh_token_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn revoke_oauth_token(
+                &self,
+                client_id: &str,
+                token: &str,
+            ) -> Result<(), ApiError> {
+                let request =
+                    crate::api_oauth_revoke_token_request(client_id, token, &self.config);
+                crate::api_oauth_revoke_token_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn retire_release(
+                &self,
+                package: &str,
+                version: &str,
+                reason: RetirementReason,
+                message: Option<&str>,
+                otp: Option<&str>,
+            ) -> Result<(), ApiError> {
+                let request = crate::api_retire_release_request(
+                    package,
+                    version,
+                    reason,
+                    message,
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                );
+                crate::api_retire_release_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn unretire_release(
+                &self,
+                package: &str,
+                version: &str,
+                otp: Option<&str>,
+            ) -> Result<(), ApiError> {
+                let request = crate::api_unretire_release_request(
+                    package,
+                    version,
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                );
+                crate::api_unretire_release_response(self.send(request)$(.$await)??)
+            }
+
+            /// Get the names of all of the packages in the repository.
+            pub $($async)? fn get_names(&self) -> Result<Vec<String>, ApiError> {
+                let request = crate::repository_v2_get_names_request(
+                    self.api_key.as_deref(),
+                    &self.config,
+                );
+                crate::repository_v2_get_names_response(
+                    self.send(request)$(.$await)??,
+                    self.public_key(),
+                )
+            }
+
+            /// Get the versions of all of the packages in the repository.
+            pub $($async)? fn get_versions(&self) -> Result<RegistryVersions, ApiError> {
+                let request = crate::repository_v2_get_versions_request(
+                    self.api_key.as_deref(),
+                    &self.config,
+                );
+                crate::repository_v2_get_registry_versions_response(
+                    self.send(request)$(.$await)??,
+                    self.public_key(),
+                )
+            }
+
+            /// Get the releases of a package from the repository.
+            pub $($async)? fn get_package(&self, name: &str) -> Result<Package, ApiError> {
+                let request = crate::repository_v2_get_package_request(
+                    name,
+                    self.api_key.as_deref(),
+                    &self.config,
+                );
+                crate::repository_v2_get_package_response(
+                    self.send(request)$(.$await)??,
+                    self.public_key(),
+                )
+            }
+
+            /// Get the releases of a package from the repository with the given name,
+            /// as found in [`Dependency::repository`](crate::Dependency::repository),
+            /// rather than the repository of the config.
+            pub $($async)? fn get_repository_package(
+                &self,
+                name: &str,
+                repository: &str,
+            ) -> Result<Package, ApiError> {
+                let mut config = self.config.clone();
+                config.set_repository_name(repository)?;
+                let request = crate::repository_v2_get_package_request(
+                    name,
+                    self.api_key.as_deref(),
+                    &config,
+                );
+                crate::repository_v2_get_package_response(
+                    self.send(request)$(.$await)??,
+                    self.public_key_for(&config),
+                )
+            }
+
+            /// Download a package tarball, checking it has the expected outer checksum.
+            pub $($async)? fn get_package_tarball(
+                &self,
+                name: &str,
+                version: &str,
+                checksum: &[u8],
+            ) -> Result<Vec<u8>, ApiError> {
+                let request = crate::repository_get_package_tarball_request(
+                    name,
+                    version,
+                    self.api_key.as_deref(),
+                    &self.config,
+                );
+                crate::repository_get_package_tarball_response(
+                    self.send(request)$(.$await)??,
+                    checksum,
+                )
+            }
+
+            /// Download the public key of the repository. See
+            /// [`trust_public_key`](crate::trust_public_key) for checking it before
+            /// it is used.
+            pub $($async)? fn get_public_key(&self) -> Result<Vec<u8>, ApiError> {
+                let request = crate::repository_get_public_key_request(
+                    self.api_key.as_deref(),
+                    &self.config,
+                );
+                crate::repository_get_public_key_response(self.send(request)$(.$await)??)
+            }
+
+            /// Download the documentation tarball of a release, which can be unpacked
+            /// with [`DocsArchive::parse`](crate::docs::DocsArchive::parse).
+            pub $($async)? fn get_docs_tarball(
+                &self,
+                name: &str,
+                version: &str,
+            ) -> Result<Vec<u8>, ApiError> {
+                let request = crate::repository_get_docs_tarball_request(
+                    name,
+                    version,
+                    self.api_key.as_deref(),
+                    &self.config,
+                )?;
+                crate::repository_get_docs_tarball_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn get_package_release(
+                &self,
+                name: &str,
+                version: &str,
+            ) -> Result<Release<ReleaseMeta>, ApiError> {
+                let request = crate::api_get_package_release_request(
+                    name,
+                    version,
+                    self.api_key.as_deref(),
+                    &self.config,
+                );
+                crate::api_get_package_release_response(self.send(request)$(.$await)??)
+            }
+
+            /// Get the metadata, owners, download counts and releases of a package
+            /// from the API.
+            pub $($async)? fn get_package_info(&self, name: &str) -> Result<PackageInfo, ApiError> {
+                let request =
+                    crate::api_get_package_request(name, self.api_key.as_deref(), &self.config)?;
+                crate::api_get_package_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn search_packages(
+                &self,
+                query: &PackageSearch,
+            ) -> Result<PackageSearchPage, ApiError> {
+                let request = crate::api_search_packages_request(
+                    query,
+                    self.api_key.as_deref(),
+                    &self.config,
+                );
+                crate::api_search_packages_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn publish_package(
+                &self,
+                release_tarball: Vec<u8>,
+                replace: bool,
+                otp: Option<&str>,
+            ) -> Result<PublishedRelease, ApiError> {
+                let request = crate::api_publish_package_request(
+                    release_tarball,
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                    replace,
+                );
+                crate::api_publish_package_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn revert_release(
+                &self,
+                package_name: &str,
+                version: &str,
+                otp: Option<&str>,
+            ) -> Result<(), ApiError> {
+                let request = crate::api_revert_release_request(
+                    package_name,
+                    version,
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                )?;
+                crate::api_revert_release_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn publish_docs(
+                &self,
+                package_name: &str,
+                version: &str,
+                gzipped_tarball: Vec<u8>,
+                otp: Option<&str>,
+            ) -> Result<(), ApiError> {
+                let request = crate::api_publish_docs_request(
+                    package_name,
+                    version,
+                    gzipped_tarball,
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                )?;
+                crate::api_publish_docs_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn remove_docs(
+                &self,
+                package_name: &str,
+                version: &str,
+                otp: Option<&str>,
+            ) -> Result<(), ApiError> {
+                let request = crate::api_remove_docs_request(
+                    package_name,
+                    version,
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                )?;
+                crate::api_remove_docs_response(self.send(request)$(.$await)??)
+            }
+
+            /// Get the user that the API key of the client belongs to.
+            pub $($async)? fn get_me(&self) -> Result<User, ApiError> {
+                let request = crate::api_get_me_request(self.required_api_key()?, &self.config);
+                crate::api_get_me_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn get_user(&self, username: &str) -> Result<User, ApiError> {
+                let request = crate::api_get_user_request(
+                    username,
+                    self.api_key.as_deref(),
+                    &self.config,
+                );
+                crate::api_get_user_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn get_user_packages(
+                &self,
+                username: &str,
+            ) -> Result<Vec<UserPackage>, ApiError> {
+                let request = crate::api_get_user_packages_request(
+                    username,
+                    self.api_key.as_deref(),
+                    &self.config,
+                );
+                crate::api_get_user_packages_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn list_owners(
+                &self,
+                package_name: &str,
+            ) -> Result<Vec<Owner>, ApiError> {
+                let request = crate::api_list_owners_request(
+                    package_name,
+                    self.api_key.as_deref(),
+                    &self.config,
+                )?;
+                crate::api_list_owners_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn add_owner(
+                &self,
+                package_name: &str,
+                owner: &str,
+                level: OwnerLevel,
+                otp: Option<&str>,
+            ) -> Result<(), ApiError> {
+                let request = crate::api_add_owner_request(
+                    package_name,
+                    owner,
+                    level,
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                );
+                crate::api_add_owner_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn transfer_owner(
+                &self,
+                package_name: &str,
+                owner: &str,
+                otp: Option<&str>,
+            ) -> Result<(), ApiError> {
+                let request = crate::api_transfer_owner_request(
+                    package_name,
+                    owner,
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                );
+                crate::api_transfer_owner_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn remove_owner(
+                &self,
+                package_name: &str,
+                owner: &str,
+                otp: Option<&str>,
+            ) -> Result<(), ApiError> {
+                let request = crate::api_remove_owner_request(
+                    package_name,
+                    owner,
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                );
+                crate::api_remove_owner_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn get_organization(
+                &self,
+                organization: &str,
+            ) -> Result<Organization, ApiError> {
+                let request = crate::api_get_organization_request(
+                    organization,
+                    self.required_api_key()?,
+                    &self.config,
+                );
+                crate::api_get_organization_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn list_organization_members(
+                &self,
+                organization: &str,
+            ) -> Result<Vec<OrganizationMember>, ApiError> {
+                let request = crate::api_list_organization_members_request(
+                    organization,
+                    self.required_api_key()?,
+                    &self.config,
+                );
+                crate::api_list_organization_members_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn get_organization_member(
+                &self,
+                organization: &str,
+                username: &str,
+            ) -> Result<OrganizationMember, ApiError> {
+                let request = crate::api_get_organization_member_request(
+                    organization,
+                    username,
+                    self.required_api_key()?,
+                    &self.config,
+                );
+                crate::api_get_organization_member_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn add_organization_member(
+                &self,
+                organization: &str,
+                username: &str,
+                role: OrganizationRole,
+                otp: Option<&str>,
+            ) -> Result<(), ApiError> {
+                let request = crate::api_add_organization_member_request(
+                    organization,
+                    username,
+                    role,
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                );
+                crate::api_add_organization_member_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn update_organization_member(
+                &self,
+                organization: &str,
+                username: &str,
+                role: OrganizationRole,
+                otp: Option<&str>,
+            ) -> Result<(), ApiError> {
+                let request = crate::api_update_organization_member_request(
+                    organization,
+                    username,
+                    role,
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                );
+                crate::api_update_organization_member_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn remove_organization_member(
+                &self,
+                organization: &str,
+                username: &str,
+                otp: Option<&str>,
+            ) -> Result<(), ApiError> {
+                let request = crate::api_remove_organization_member_request(
+                    organization,
+                    username,
+                    self.required_api_key()?,
+                    otp,
+                    &self.config,
+                );
+                crate::api_remove_organization_member_response(self.send(request)$(.$await)??)
+            }
+
+            pub $($async)? fn get_organization_audit_logs(
+                &self,
+                organization: &str,
+                page: u32,
+            ) -> Result<Vec<AuditLog>, ApiError> {
+                let request = crate::api_get_organization_audit_logs_request(
+                    organization,
+                    page,
+                    self.required_api_key()?,
+                    &self.config,
+                );
+                crate::api_get_organization_audit_logs_response(self.send(request)$(.$await)??)
+            }
+        }
+    };
+}
+
+define_client! {
+    /// A client for the Hex API and repository using a blocking transport.
+    Client<HttpClient>
+}
+
+define_client! {
+    /// A client for the Hex API and repository using an asynchronous transport.
+    ///
+    /// The methods are the same as those of [`Client`].
+    AsyncClient<AsyncHttpClient>, async await
+}
@@ -0,0 +1,334 @@
+use std::sync::Mutex;
+
+use serde_json::json;
+
+use super::*;
+use crate::registry::{PrivateKey, RegistryBuilder};
+use crate::tarball::{PackageMetadata, TarballBuilder};
+use crate::version::Version;
+
+/// A transport that records the requests sent and replies with queued
+/// responses.
+#[derive(Debug, Default)]
+struct MockTransport {
+    requests: Mutex<Vec<http::Request<Vec<u8>>>>,
+    responses: Mutex<Vec<http::Response<Vec<u8>>>>,
+}
+
+impl MockTransport {
+    fn replying(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        let transport = Self::default();
+        transport.push_response(status, body);
+        transport
+    }
+
+    fn push_response(&self, status: u16, body: impl Into<Vec<u8>>) {
+        let response = http::Response::builder()
+            .status(status)
+            .body(body.into())
+            .unwrap();
+        self.responses.lock().unwrap().insert(0, response);
+    }
+
+    fn respond(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> Result<http::Response<Vec<u8>>, std::io::Error> {
+        self.requests.lock().unwrap().push(request);
+        self.responses
+            .lock()
+            .unwrap()
+            .pop()
+            .ok_or_else(|| std::io::Error::other("no response queued"))
+    }
+
+    fn sent(&self) -> Vec<(http::Method, String)> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| (request.method().clone(), request.uri().to_string()))
+            .collect()
+    }
+}
+
+impl HttpClient for MockTransport {
+    type Error = std::io::Error;
+
+    fn send(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> Result<http::Response<Vec<u8>>, Self::Error> {
+        self.respond(request)
+    }
+}
+
+impl AsyncHttpClient for MockTransport {
+    type Error = std::io::Error;
+
+    fn send(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> impl Future<Output = Result<http::Response<Vec<u8>>, Self::Error>> + Send {
+        let response = self.respond(request);
+        async move { response }
+    }
+}
+
+fn example_registry() -> crate::registry::Registry {
+    let metadata = PackageMetadata::new("my_package", Version::new(1, 0, 0));
+    let tarball = TarballBuilder::new(metadata).build().unwrap();
+    let private_key =
+        PrivateKey::from_pem(std::include_bytes!("../../test/example_private_key")).unwrap();
+    RegistryBuilder::new("hexpm")
+        .add_tarball(&tarball.data)
+        .unwrap()
+        .build(&private_key)
+        .unwrap()
+}
+
+#[test]
+fn get_package() {
+    let registry = example_registry();
+    let transport = MockTransport::replying(200, registry.packages["my_package"].clone());
+    let mut client = Client::new(transport);
+    client.repository_public_key = registry.public_key.into_bytes();
+
+    let package = client.get_package("my_package").unwrap();
+
+    assert_eq!(package.name, "my_package");
+    assert_eq!(package.releases[0].version, Version::new(1, 0, 0));
+    assert_eq!(
+        client.transport.sent(),
+        vec![(
+            http::Method::GET,
+            "https://repo.hex.pm/packages/my_package".to_string()
+        )]
+    );
+}
+
+#[test]
+fn get_organization_package() {
+    let registry = example_registry();
+    let transport = MockTransport::replying(200, registry.packages["my_package"].clone());
+    let mut client = Client::new(transport);
+    client.config = Config::for_organization("my_org").unwrap();
+    let _ = client
+        .organization_public_keys
+        .insert("my_org".into(), registry.public_key.into_bytes());
+
+    let package = client.get_package("my_package").unwrap();
+
+    assert_eq!(package.name, "my_package");
+    assert_eq!(
+        client.transport.sent(),
+        vec![(
+            http::Method::GET,
+            "https://repo.hex.pm/repos/my_org/packages/my_package".to_string()
+        )]
+    );
+}
+
+#[test]
+fn get_repository_package() {
+    let registry = example_registry();
+    let transport = MockTransport::replying(200, registry.packages["my_package"].clone());
+    let mut client = Client::new(transport);
+    let _ = client
+        .organization_public_keys
+        .insert("my_org".into(), registry.public_key.clone().into_bytes());
+
+    let package = client
+        .get_repository_package("my_package", "hexpm:my_org")
+        .unwrap();
+    assert_eq!(package.name, "my_package");
+
+    // The public repository is checked with the hex.pm key.
+    client.config = Config::for_organization("my_org").unwrap();
+    client
+        .transport
+        .push_response(200, registry.packages["my_package"].clone());
+    assert!(matches!(
+        client.get_repository_package("my_package", "hexpm"),
+        Err(ApiError::IncorrectPayloadSignature)
+    ));
+
+    assert_eq!(
+        client.transport.sent(),
+        vec![
+            (
+                http::Method::GET,
+                "https://repo.hex.pm/repos/my_org/packages/my_package".to_string()
+            ),
+            (
+                http::Method::GET,
+                "https://repo.hex.pm/packages/my_package".to_string()
+            ),
+        ]
+    );
+    assert!(matches!(
+        client.get_repository_package("my_package", "my_mirror"),
+        Err(ApiError::UnsupportedRepository(name)) if name == "my_mirror"
+    ));
+}
+
+#[test]
+fn get_names_with_wrong_public_key() {
+    let registry = example_registry();
+    let client = Client::new(MockTransport::replying(200, registry.names));
+
+    let error = client.get_names().unwrap_err();
+
+    assert!(matches!(error, ApiError::IncorrectPayloadSignature));
+}
+
+#[test]
+fn authenticated_request() {
+    let mut client = Client::new(MockTransport::replying(204, vec![]));
+    client.api_key = Some("my-api-key".into());
+    client.config.api_base = http::Uri::from_static("http://localhost:4000/api/");
+
+    client
+        .add_owner("my_package", "lpil", OwnerLevel::Maintainer, Some("123456"))
+        .unwrap();
+
+    let requests = client.transport.requests.lock().unwrap();
+    let request = &requests[0];
+    assert_eq!(request.method(), http::Method::PUT);
+    assert_eq!(
+        request.uri(),
+        "http://localhost:4000/api/packages/my_package/owners/lpil"
+    );
+    assert_eq!(request.headers()["authorization"], "my-api-key");
+    assert_eq!(request.headers()["x-hex-otp"], "123456");
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(request.body()).unwrap(),
+        json!({"level": "maintainer", "transfer": false})
+    );
+}
+
+#[test]
+fn missing_api_key() {
+    let client = Client::new(MockTransport::default());
+
+    let error = client.remove_owner("my_package", "lpil", None).unwrap_err();
+
+    assert!(matches!(error, ApiError::MissingApiKey));
+    assert!(client.transport.sent().is_empty());
+}
+
+#[test]
+fn transport_error() {
+    let client = Client::new(MockTransport::default());
+
+    let error = client.get_versions().unwrap_err();
+
+    assert!(matches!(error, ApiError::Transport(_)));
+    assert_eq!(
+        error.to_string(),
+        "the request could not be sent: no response queued"
+    );
+}
+
+#[test]
+fn api_error_response() {
+    let mut client = Client::new(MockTransport::replying(429, vec![]));
+    client.api_key = Some("my-api-key".into());
+
+    let error = client
+        .revert_release("my_package", "1.0.0", None)
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        ApiError::RateLimited {
+            rate_limit: None,
+            retry_after: None,
+            body: None
+        }
+    ));
+}
+
+#[test]
+fn rate_limit() {
+    let rate_limited = |status: u16, remaining: &str| {
+        http::Response::builder()
+            .status(status)
+            .header("x-ratelimit-limit", "500")
+            .header("x-ratelimit-remaining", remaining)
+            .header("x-ratelimit-reset", "1700000000")
+            .body(vec![])
+            .unwrap()
+    };
+    let transport = MockTransport::default();
+    transport
+        .responses
+        .lock()
+        .unwrap()
+        .extend([rate_limited(429, "0"), rate_limited(204, "1")]);
+    let mut client = Client::new(transport);
+    client.api_key = Some("my-api-key".into());
+    assert_eq!(client.rate_limit(), None);
+
+    client.revert_release("my_package", "1.0.0", None).unwrap();
+    assert_eq!(
+        client.rate_limit(),
+        Some(RateLimit {
+            limit: 500,
+            remaining: 1,
+            reset: 1700000000,
+        })
+    );
+
+    let error = client
+        .revert_release("my_package", "1.0.0", None)
+        .unwrap_err();
+    let rate_limit = RateLimit {
+        limit: 500,
+        remaining: 0,
+        reset: 1700000000,
+    };
+    assert_eq!(error.rate_limit(), Some(&rate_limit));
+    assert_eq!(client.rate_limit(), Some(rate_limit));
+    assert_eq!(
+        rate_limit.reset_at(),
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(1700000000)
+    );
+}
+
+#[tokio::test]
+async fn async_get_versions() {
+    let registry = example_registry();
+    let transport = MockTransport::replying(200, registry.versions);
+    let mut client = AsyncClient::new(transport);
+    client.repository_public_key = registry.public_key.into_bytes();
+
+    let versions = client.get_versions().await.unwrap();
+
+    assert_eq!(
+        versions
+            .unretired_versions("my_package")
+            .collect::<Vec<_>>(),
+        vec![&Version::new(1, 0, 0)]
+    );
+    assert_eq!(
+        client.transport.sent(),
+        vec![(
+            http::Method::GET,
+            "https://repo.hex.pm/versions".to_string()
+        )]
+    );
+}
+
+#[tokio::test]
+async fn async_create_api_key() {
+    let body = json!({"secret": "some-secret-here"}).to_string();
+    let client = AsyncClient::new(MockTransport::replying(201, body));
+
+    let secret = client
+        .create_api_key("me@example.com", "password", "my-key", None)
+        .await
+        .unwrap();
+
+    assert_eq!(secret, "some-secret-here");
+}
@@ -0,0 +1,511 @@
+//! Parsing and writing of Erlang terms in the `file:consult` format, as used
+//! by the `metadata.config` file of package tarballs.
+//!
+//! ```erlang
+//! {<<"name">>,<<"gleam_stdlib">>}.
+//! {<<"licenses">>,[<<"Apache 2.0">>]}.
+//! ```
+//!
+//! https://www.erlang.org/doc/man/file.html#consult-1
+
+#[cfg(test)]
+mod tests;
+
+use std::{fmt, iter::Peekable, str::CharIndices};
+
+use thiserror::Error;
+
+/// An Erlang term.
+///
+/// Terms are displayed using Erlang syntax, i.e. `{<<"name">>,<<"gleam_stdlib">>}`.
+/// Erlang has no NaN or infinite floats, so these are displayed as `NaN` and
+/// `inf`, which cannot be parsed. [`to_string`] rejects them instead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    /// An atom such as `true` or `'hello world'`.
+    Atom(String),
+    /// A binary such as `<<"hello">>` or `<<1,2,3>>`.
+    Binary(Vec<u8>),
+    /// A string literal, which in Erlang is a list of characters.
+    String(String),
+    Integer(i64),
+    Float(f64),
+    List(Vec<Term>),
+    Tuple(Vec<Term>),
+    Map(Vec<(Term, Term)>),
+}
+
+impl Term {
+    /// A binary holding the given UTF-8 text.
+    pub fn binary(value: &str) -> Self {
+        Term::Binary(value.as_bytes().to_vec())
+    }
+
+    /// The contents of a binary that holds valid UTF-8 text.
+    pub fn as_binary_str(&self) -> Option<&str> {
+        match self {
+            Term::Binary(bytes) => std::str::from_utf8(bytes).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_atom(&self) -> Option<&str> {
+        match self {
+            Term::Atom(atom) => Some(atom),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.as_atom() {
+            Some("true") => Some(true),
+            Some("false") => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Term]> {
+        match self {
+            Term::List(elements) => Some(elements),
+            _ => None,
+        }
+    }
+
+    pub fn as_tuple(&self) -> Option<&[Term]> {
+        match self {
+            Term::Tuple(elements) => Some(elements),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Term::Atom(atom) => {
+                if is_bare_atom(atom) {
+                    f.write_str(atom)
+                } else {
+                    write_quoted(f, atom, '\'')
+                }
+            }
+            Term::Binary(bytes) => match std::str::from_utf8(bytes) {
+                Ok(text) => {
+                    f.write_str("<<")?;
+                    write_quoted(f, text, '"')?;
+                    if !text.is_ascii() {
+                        f.write_str("/utf8")?;
+                    }
+                    f.write_str(">>")
+                }
+                Err(_) => {
+                    f.write_str("<<")?;
+                    write_separated(f, bytes)?;
+                    f.write_str(">>")
+                }
+            },
+            Term::String(string) => write_quoted(f, string, '"'),
+            Term::Integer(integer) => write!(f, "{}", integer),
+            Term::Float(float) => {
+                // Erlang requires a fraction before any exponent, i.e. `1.0e20`
+                let text = format!("{:?}", float);
+                match text.split_once('e') {
+                    Some((mantissa, exponent)) if !mantissa.contains('.') => {
+                        write!(f, "{}.0e{}", mantissa, exponent)
+                    }
+                    _ => f.write_str(&text),
+                }
+            }
+            Term::List(elements) => {
+                f.write_str("[")?;
+                write_separated(f, elements)?;
+                f.write_str("]")
+            }
+            Term::Tuple(elements) => {
+                f.write_str("{")?;
+                write_separated(f, elements)?;
+                f.write_str("}")
+            }
+            Term::Map(pairs) => {
+                f.write_str("#{")?;
+                for (i, (key, value)) in pairs.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{} => {}", key, value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+fn write_separated(f: &mut fmt::Formatter<'_>, elements: &[impl fmt::Display]) -> fmt::Result {
+    for (i, element) in elements.iter().enumerate() {
+        if i != 0 {
+            f.write_str(",")?;
+        }
+        write!(f, "{}", element)?;
+    }
+    Ok(())
+}
+
+fn write_quoted(f: &mut fmt::Formatter<'_>, text: &str, quote: char) -> fmt::Result {
+    use fmt::Write;
+    f.write_char(quote)?;
+    for c in text.chars() {
+        match c {
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            '\r' => f.write_str("\\r")?,
+            c if c == quote => {
+                f.write_char('\\')?;
+                f.write_char(c)?;
+            }
+            c if c.is_control() => write!(f, "\\x{{{:X}}}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char(quote)
+}
+
+/// Whether an atom can be written without quotes.
+fn is_bare_atom(atom: &str) -> bool {
+    const RESERVED: &[&str] = &[
+        "after", "and", "andalso", "band", "begin", "bnot", "bor", "bsl", "bsr", "bxor", "case",
+        "catch", "cond", "div", "else", "end", "fun", "if", "let", "maybe", "not", "of", "or",
+        "orelse", "receive", "rem", "try", "when", "xor",
+    ];
+    let mut chars = atom.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '@')
+        && !RESERVED.contains(&atom)
+}
+
+/// Write a sequence of terms in the format read by Erlang's `file:consult/1`,
+/// each on its own line and terminated by a `.`.
+///
+/// The output is deterministic, and parsing it with [`parse`] returns the
+/// same terms. Terms containing a NaN or infinite float cannot be written.
+pub fn to_string(terms: &[Term]) -> Result<String, WriteError> {
+    terms.iter().try_for_each(check_floats)?;
+    Ok(terms.iter().map(|term| format!("{}.\n", term)).collect())
+}
+
+fn check_floats(term: &Term) -> Result<(), WriteError> {
+    match term {
+        Term::Float(float) if !float.is_finite() => Err(WriteError::NonFiniteFloat(*float)),
+        Term::List(elements) | Term::Tuple(elements) => elements.iter().try_for_each(check_floats),
+        Term::Map(pairs) => pairs
+            .iter()
+            .try_for_each(|(key, value)| check_floats(key).and_then(|_| check_floats(value))),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum WriteError {
+    #[error("the float {0} cannot be written as Erlang has no NaN or infinity")]
+    NonFiniteFloat(f64),
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    #[error("unexpected end of input")]
+    UnexpectedEof,
+
+    #[error("unexpected character {1:?} at byte {0}")]
+    UnexpectedChar(usize, char),
+
+    #[error("invalid number at byte {0}")]
+    InvalidNumber(usize),
+
+    #[error("invalid binary segment at byte {0}")]
+    InvalidBinarySegment(usize),
+}
+
+/// Parse a sequence of terms, each terminated by a `.`, as read by
+/// Erlang's `file:consult/1`.
+pub fn parse(input: &str) -> Result<Vec<Term>, ParseError> {
+    let mut parser = Parser {
+        input,
+        chars: input.char_indices().peekable(),
+    };
+    let mut terms = Vec::new();
+    loop {
+        parser.skip_whitespace();
+        if parser.chars.peek().is_none() {
+            return Ok(terms);
+        }
+        terms.push(parser.term()?);
+        parser.skip_whitespace();
+        parser.expect('.')?;
+    }
+}
+
+struct Parser<'input> {
+    input: &'input str,
+    chars: Peekable<CharIndices<'input>>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while let Some(&(_, c)) = self.chars.peek() {
+            if c == '%' {
+                for (_, c) in self.chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            } else if c.is_whitespace() {
+                let _ = self.chars.next();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn next(&mut self) -> Result<(usize, char), ParseError> {
+        self.chars.next().ok_or(ParseError::UnexpectedEof)
+    }
+
+    fn peek(&mut self) -> Result<(usize, char), ParseError> {
+        self.chars.peek().copied().ok_or(ParseError::UnexpectedEof)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+        match self.next()? {
+            (_, c) if c == expected => Ok(()),
+            (i, c) => Err(ParseError::UnexpectedChar(i, c)),
+        }
+    }
+
+    fn term(&mut self) -> Result<Term, ParseError> {
+        self.skip_whitespace();
+        let (i, c) = self.peek()?;
+        match c {
+            '{' => {
+                let _ = self.next()?;
+                Ok(Term::Tuple(self.sequence('}')?))
+            }
+            '[' => {
+                let _ = self.next()?;
+                Ok(Term::List(self.sequence(']')?))
+            }
+            '#' => {
+                let _ = self.next()?;
+                self.expect('{')?;
+                self.map()
+            }
+            '<' => {
+                let _ = self.next()?;
+                self.expect('<')?;
+                self.binary()
+            }
+            '"' => Ok(Term::String(self.quoted('"')?)),
+            '\'' => Ok(Term::Atom(self.quoted('\'')?)),
+            '-' | '0'..='9' => self.number(),
+            c if c.is_lowercase() => Ok(Term::Atom(self.bare_atom())),
+            c => Err(ParseError::UnexpectedChar(i, c)),
+        }
+    }
+
+    /// Comma separated terms up to and including the closing character.
+    fn sequence(&mut self, close: char) -> Result<Vec<Term>, ParseError> {
+        let mut elements = Vec::new();
+        self.skip_whitespace();
+        if self.peek()?.1 == close {
+            let _ = self.next()?;
+            return Ok(elements);
+        }
+        loop {
+            elements.push(self.term()?);
+            self.skip_whitespace();
+            match self.next()? {
+                (_, ',') => continue,
+                (_, c) if c == close => return Ok(elements),
+                (i, c) => return Err(ParseError::UnexpectedChar(i, c)),
+            }
+        }
+    }
+
+    fn map(&mut self) -> Result<Term, ParseError> {
+        let mut pairs = Vec::new();
+        self.skip_whitespace();
+        if self.peek()?.1 == '}' {
+            let _ = self.next()?;
+            return Ok(Term::Map(pairs));
+        }
+        loop {
+            let key = self.term()?;
+            self.skip_whitespace();
+            self.expect('=')?;
+            self.expect('>')?;
+            let value = self.term()?;
+            pairs.push((key, value));
+            self.skip_whitespace();
+            match self.next()? {
+                (_, ',') => continue,
+                (_, '}') => return Ok(Term::Map(pairs)),
+                (i, c) => return Err(ParseError::UnexpectedChar(i, c)),
+            }
+        }
+    }
+
+    /// The contents of a binary after the opening `<<`.
+    fn binary(&mut self) -> Result<Term, ParseError> {
+        let mut bytes = Vec::new();
+        self.skip_whitespace();
+        if self.peek()?.1 != '>' {
+            loop {
+                self.skip_whitespace();
+                let (i, c) = self.peek()?;
+                match c {
+                    '"' => {
+                        let string = self.quoted('"')?;
+                        // Without a type specifier each character is truncated to a byte.
+                        if self.peek()?.1 == '/' {
+                            let _ = self.next()?;
+                            if self.bare_atom() != "utf8" {
+                                return Err(ParseError::InvalidBinarySegment(i));
+                            }
+                            bytes.extend_from_slice(string.as_bytes());
+                        } else {
+                            bytes.extend(string.chars().map(|c| c as u32 as u8));
+                        }
+                    }
+                    '0'..='9' => match self.number()? {
+                        Term::Integer(n) if (0..=255).contains(&n) => bytes.push(n as u8),
+                        _ => return Err(ParseError::InvalidBinarySegment(i)),
+                    },
+                    c => return Err(ParseError::UnexpectedChar(i, c)),
+                }
+                self.skip_whitespace();
+                if self.peek()?.1 == ',' {
+                    let _ = self.next()?;
+                } else {
+                    break;
+                }
+            }
+        }
+        self.expect('>')?;
+        self.expect('>')?;
+        Ok(Term::Binary(bytes))
+    }
+
+    fn bare_atom(&mut self) -> String {
+        let mut atom = String::new();
+        while let Some(&(_, c)) = self.chars.peek() {
+            if c.is_alphanumeric() || c == '_' || c == '@' {
+                atom.push(c);
+                let _ = self.chars.next();
+            } else {
+                break;
+            }
+        }
+        atom
+    }
+
+    fn number(&mut self) -> Result<Term, ParseError> {
+        let (start, _) = self.next()?;
+        let mut end = start + 1;
+        let mut is_float = false;
+        while let Some(&(i, c)) = self.chars.peek() {
+            if c.is_ascii_digit() {
+                end = i + 1;
+                let _ = self.chars.next();
+            } else if c == '.' && !is_float {
+                // A dot is only part of the number if followed by a digit,
+                // otherwise it terminates the term.
+                let mut lookahead = self.chars.clone();
+                let _ = lookahead.next();
+                match lookahead.peek() {
+                    Some((_, c)) if c.is_ascii_digit() => {
+                        is_float = true;
+                        let _ = self.chars.next();
+                    }
+                    _ => break,
+                }
+            } else if (c == 'e' || c == 'E') && is_float {
+                let _ = self.chars.next();
+                if let Some(&(_, '-' | '+')) = self.chars.peek() {
+                    let _ = self.chars.next();
+                }
+                end = i + 1;
+            } else {
+                break;
+            }
+        }
+        let text = &self.input[start..end];
+        let term = if is_float {
+            text.parse().ok().map(Term::Float)
+        } else {
+            text.parse().ok().map(Term::Integer)
+        };
+        term.ok_or(ParseError::InvalidNumber(start))
+    }
+
+    /// A string or quoted atom, including the surrounding quotes.
+    fn quoted(&mut self, quote: char) -> Result<String, ParseError> {
+        self.expect(quote)?;
+        let mut string = String::new();
+        loop {
+            match self.next()? {
+                (_, c) if c == quote => return Ok(string),
+                (_, '\\') => string.push(self.escape()?),
+                (_, c) => string.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, ParseError> {
+        let (i, c) = self.next()?;
+        let c = match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            's' => ' ',
+            'e' => '\x1b',
+            'b' => '\x08',
+            'f' => '\x0c',
+            'v' => '\x0b',
+            'd' => '\x7f',
+            'x' => {
+                let mut digits = String::new();
+                if self.peek()?.1 == '{' {
+                    let _ = self.next()?;
+                    loop {
+                        match self.next()? {
+                            (_, '}') => break,
+                            (_, c) => digits.push(c),
+                        }
+                    }
+                } else {
+                    for _ in 0..2 {
+                        digits.push(self.next()?.1);
+                    }
+                }
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or(ParseError::UnexpectedChar(i, c))?
+            }
+            '0'..='7' => {
+                let mut value = c.to_digit(8).unwrap_or_default();
+                for _ in 0..2 {
+                    match self.chars.peek() {
+                        Some(&(_, d)) if d.is_digit(8) => {
+                            value = value * 8 + d.to_digit(8).unwrap_or_default();
+                            let _ = self.chars.next();
+                        }
+                        _ => break,
+                    }
+                }
+                char::from_u32(value).ok_or(ParseError::UnexpectedChar(i, c))?
+            }
+            c => c,
+        };
+        Ok(c)
+    }
+}
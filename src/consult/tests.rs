@@ -0,0 +1,158 @@
+use super::*;
+
+fn binary(value: &str) -> Term {
+    Term::binary(value)
+}
+
+#[test]
+fn parse_metadata_entries() {
+    let input = r#"{<<"app">>,<<"gleam_stdlib">>}.
+{<<"build_tools">>,[<<"rebar3">>]}.
+{<<"description">>,
+ <<"A standard library for the Gleam programming language">>}.
+"#;
+    assert_eq!(
+        parse(input).unwrap(),
+        vec![
+            Term::Tuple(vec![binary("app"), binary("gleam_stdlib")]),
+            Term::Tuple(vec![
+                binary("build_tools"),
+                Term::List(vec![binary("rebar3")])
+            ]),
+            Term::Tuple(vec![
+                binary("description"),
+                binary("A standard library for the Gleam programming language")
+            ]),
+        ]
+    );
+}
+
+#[test]
+fn parse_scalars() {
+    assert_eq!(
+        parse("ok. 'hello world'. \"chars\". -12. 1.5. 2.0e3.").unwrap(),
+        vec![
+            Term::Atom("ok".into()),
+            Term::Atom("hello world".into()),
+            Term::String("chars".into()),
+            Term::Integer(-12),
+            Term::Float(1.5),
+            Term::Float(2000.0),
+        ]
+    );
+}
+
+#[test]
+fn parse_binaries() {
+    assert_eq!(
+        parse(r#"<<>>. <<1,2,255>>. <<"héllo"/utf8>>. <<"a\"b\\c\n">>."#).unwrap(),
+        vec![
+            Term::Binary(vec![]),
+            Term::Binary(vec![1, 2, 255]),
+            binary("héllo"),
+            binary("a\"b\\c\n"),
+        ]
+    );
+}
+
+#[test]
+fn parse_collections() {
+    assert_eq!(
+        parse("{}. []. [a, {b, 1}]. #{a => 1, <<\"b\">> => []}.").unwrap(),
+        vec![
+            Term::Tuple(vec![]),
+            Term::List(vec![]),
+            Term::List(vec![
+                Term::Atom("a".into()),
+                Term::Tuple(vec![Term::Atom("b".into()), Term::Integer(1)])
+            ]),
+            Term::Map(vec![
+                (Term::Atom("a".into()), Term::Integer(1)),
+                (binary("b"), Term::List(vec![])),
+            ]),
+        ]
+    );
+}
+
+#[test]
+fn parse_comments() {
+    assert_eq!(
+        parse("% A comment\n{a, % inline\n b}.\n").unwrap(),
+        vec![Term::Tuple(vec![
+            Term::Atom("a".into()),
+            Term::Atom("b".into())
+        ])]
+    );
+}
+
+#[test]
+fn parse_escapes() {
+    assert_eq!(
+        parse(r#""\x41\x{1F600}\101\s"."#).unwrap(),
+        vec![Term::String("A😀A ".into())]
+    );
+}
+
+#[test]
+fn parse_missing_terminator() {
+    assert_eq!(parse("{a, b}"), Err(ParseError::UnexpectedEof));
+}
+
+#[test]
+fn parse_unexpected_char() {
+    assert_eq!(parse("{a; b}."), Err(ParseError::UnexpectedChar(2, ';')));
+}
+
+#[test]
+fn parse_byte_out_of_range() {
+    assert_eq!(parse("<<256>>."), Err(ParseError::InvalidBinarySegment(2)));
+}
+
+#[test]
+fn write_terms() {
+    let terms = vec![
+        Term::Tuple(vec![binary("name"), binary("héllo")]),
+        Term::List(vec![
+            Term::Atom("ok".into()),
+            Term::Atom("Not bare".into()),
+            Term::Atom("end".into()),
+        ]),
+        Term::Binary(vec![0, 159, 146, 150]),
+        Term::Map(vec![(Term::Integer(1), Term::Float(1e20))]),
+        Term::String("tab\there".into()),
+    ];
+    assert_eq!(
+        to_string(&terms).unwrap(),
+        r#"{<<"name">>,<<"héllo"/utf8>>}.
+[ok,'Not bare','end'].
+<<0,159,146,150>>.
+#{1 => 1.0e20}.
+"tab\there".
+"#
+    );
+}
+
+#[test]
+fn write_then_parse_round_trips() {
+    let terms = vec![
+        Term::Tuple(vec![binary("quote\"s"), binary("\u{1}control")]),
+        Term::List(vec![Term::Float(-0.25), Term::Integer(i64::MIN)]),
+        Term::Atom("it's".into()),
+        Term::Map(vec![]),
+    ];
+    assert_eq!(parse(&to_string(&terms).unwrap()).unwrap(), terms);
+}
+
+#[test]
+fn write_non_finite_floats() {
+    for float in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        let terms = vec![Term::Tuple(vec![
+            Term::Atom("ok".into()),
+            Term::List(vec![Term::Float(float)]),
+        ])];
+        assert!(matches!(
+            to_string(&terms),
+            Err(WriteError::NonFiniteFloat(f)) if f.to_bits() == float.to_bits()
+        ));
+    }
+}
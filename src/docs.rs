@@ -0,0 +1,224 @@
+//! Construction and reading of HexDocs documentation tarballs, as uploaded with
+//! [`api_publish_docs_request`](crate::api_publish_docs_request) and
+//! downloaded with
+//! [`repository_get_docs_tarball_request`](crate::repository_get_docs_tarball_request).
+//!
+//! A documentation tarball is a gzipped tar file of the HTML documentation of
+//! a release, with `index.html` at its root.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use thiserror::Error;
+
+use crate::tarball::{append_entry, is_valid_path};
+
+/// The largest uncompressed documentation that will be built or read,
+/// matching the limit used by Hex.
+pub const DOCS_MAX_UNCOMPRESSED_SIZE: u64 = 128 * 1024 * 1024;
+
+/// The largest gzipped documentation tarball that will be built, matching the
+/// limit used by Hex.
+pub const DOCS_MAX_COMPRESSED_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("the file path {0} is not valid in a documentation tarball")]
+    InvalidPath(String),
+
+    #[error("the documentation tarball entry {0} is a link or special file")]
+    UnsupportedEntry(String),
+
+    #[error("the documentation exceeds the maximum uncompressed size")]
+    DocsTooLarge,
+
+    #[error("the documentation tarball exceeds the maximum size")]
+    TarballTooLarge,
+}
+
+/// Builds a gzipped documentation tarball, to be uploaded with
+/// [`api_publish_docs_request`](crate::api_publish_docs_request).
+///
+/// Files are written in order of their paths, with the same modification
+/// time and permissions, so the same files always produce the same tarball.
+///
+/// ```
+/// use hexpm::docs::DocsTarballBuilder;
+///
+/// let tarball = DocsTarballBuilder::new()
+///     .add_file("index.html", "<h1>my_package</h1>")
+///     .add_file("dist/app.js", "main()")
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(&tarball[..2], [0x1f, 0x8b]);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct DocsTarballBuilder {
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl DocsTarballBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file to the documentation. The path must be relative and use `/`
+    /// as the separator, and any leading `./` is removed. Adding a path a
+    /// second time replaces the previously added contents.
+    pub fn add_file(&mut self, path: impl Into<String>, contents: impl Into<Vec<u8>>) -> &mut Self {
+        let path = path.into();
+        let path = match path.strip_prefix("./") {
+            Some(path) => path.to_string(),
+            None => path,
+        };
+        let _ = self.files.insert(path, contents.into());
+        self
+    }
+
+    /// Add every file within a directory, such as the output directory of a
+    /// documentation generator, with paths relative to the directory.
+    /// Directories containing links or special files are rejected.
+    pub fn add_directory(&mut self, directory: impl AsRef<Path>) -> Result<&mut Self, Error> {
+        self.add_directory_files(directory.as_ref(), "")?;
+        Ok(self)
+    }
+
+    fn add_directory_files(&mut self, directory: &Path, prefix: &str) -> Result<(), Error> {
+        for entry in std::fs::read_dir(directory)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let path = format!("{}{}", prefix, name.to_string_lossy());
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                self.add_directory_files(&entry.path(), &format!("{}/", path))?;
+            } else if file_type.is_file() {
+                let _ = self.add_file(path, std::fs::read(entry.path())?);
+            } else {
+                return Err(Error::UnsupportedEntry(path));
+            }
+        }
+        Ok(())
+    }
+
+    /// Produce the gzipped tarball, checking it is within the size limits of
+    /// Hex.
+    pub fn build(&self) -> Result<Vec<u8>, Error> {
+        let mut tarball = tar::Builder::new(Vec::new());
+        for (path, contents) in &self.files {
+            if !is_valid_path(path) {
+                return Err(Error::InvalidPath(path.clone()));
+            }
+            append_entry(&mut tarball, path, contents)?;
+        }
+        let tarball = tarball.into_inner()?;
+        if tarball.len() as u64 > DOCS_MAX_UNCOMPRESSED_SIZE {
+            return Err(Error::DocsTooLarge);
+        }
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&tarball)?;
+        let data = encoder.finish()?;
+        if data.len() as u64 > DOCS_MAX_COMPRESSED_SIZE {
+            return Err(Error::TarballTooLarge);
+        }
+        Ok(data)
+    }
+}
+
+/// A file of the documentation of a release.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DocsFile {
+    /// The relative path of the file, using `/` as the separator.
+    pub path: String,
+    pub contents: Vec<u8>,
+}
+
+/// A documentation tarball that has been unpacked into memory.
+///
+/// ```no_run
+/// # fn download(_: &str) -> Vec<u8> { unimplemented!() }
+/// use hexpm::docs::DocsArchive;
+///
+/// let archive = DocsArchive::parse(&download("gleam_stdlib-0.14.0.tar.gz")).unwrap();
+/// for file in archive.files() {
+///     println!("{}: {} bytes", file.path, file.contents.len());
+/// }
+/// archive.unpack("docs/gleam_stdlib/0.14.0").unwrap();
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DocsArchive {
+    files: Vec<DocsFile>,
+}
+
+impl DocsArchive {
+    /// Unpack a gzipped documentation tarball, rejecting any entries that are
+    /// links or that have paths that would escape the directory they are
+    /// unpacked into. A leading `./` is removed from paths.
+    pub fn parse(gzipped: &[u8]) -> Result<Self, Error> {
+        let mut tarball = Vec::new();
+        let _ = GzDecoder::new(gzipped)
+            .take(DOCS_MAX_UNCOMPRESSED_SIZE + 1)
+            .read_to_end(&mut tarball)?;
+        if tarball.len() as u64 > DOCS_MAX_UNCOMPRESSED_SIZE {
+            return Err(Error::DocsTooLarge);
+        }
+
+        let mut files = Vec::new();
+        for entry in tar::Archive::new(tarball.as_slice()).entries()? {
+            let mut entry = entry?;
+            let raw_path = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+            let path = raw_path.trim_start_matches("./").trim_end_matches('/');
+            match entry.header().entry_type() {
+                tar::EntryType::Directory if path.is_empty() || is_valid_path(path) => continue,
+                tar::EntryType::Regular | tar::EntryType::Continuous => (),
+                tar::EntryType::Directory => return Err(Error::InvalidPath(raw_path)),
+                _ => return Err(Error::UnsupportedEntry(raw_path)),
+            }
+            if !is_valid_path(path) {
+                return Err(Error::InvalidPath(raw_path));
+            }
+            let path = path.to_string();
+            let mut contents = Vec::new();
+            let _ = entry.read_to_end(&mut contents)?;
+            files.push(DocsFile { path, contents });
+        }
+        Ok(Self { files })
+    }
+
+    /// The files of the documentation, in the order they appear in the
+    /// tarball.
+    pub fn files(&self) -> std::slice::Iter<'_, DocsFile> {
+        self.files.iter()
+    }
+
+    /// The contents of the file at the given path, if there is one.
+    pub fn file(&self, path: &str) -> Option<&[u8]> {
+        self.files
+            .iter()
+            .find(|file| file.path == path)
+            .map(|file| file.contents.as_slice())
+    }
+
+    /// Write the files into a directory, creating it and any subdirectories
+    /// as needed. Existing files are overwritten.
+    pub fn unpack(&self, directory: impl AsRef<Path>) -> Result<(), Error> {
+        let directory = directory.as_ref();
+        for file in &self.files {
+            let path = directory.join(&file.path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, &file.contents)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+use std::io::Write;
+
+use flate2::{Compression, write::GzEncoder};
+
+use super::*;
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// A gzipped tarball with a header for each entry, written without the
+/// checks of `tar::Builder` so that invalid paths can be included.
+fn docs_tarball(entries: &[(&str, tar::EntryType, &[u8])]) -> Vec<u8> {
+    let mut tarball = tar::Builder::new(Vec::new());
+    for (path, entry_type, contents) in entries {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_entry_type(*entry_type);
+        header.set_cksum();
+        tarball.append(&header, *contents).unwrap();
+    }
+    gzip(&tarball.into_inner().unwrap())
+}
+
+#[test]
+fn parse_docs() {
+    let data = docs_tarball(&[
+        ("./", tar::EntryType::Directory, b""),
+        (
+            "./index.html",
+            tar::EntryType::Regular,
+            b"<h1>my_package</h1>",
+        ),
+        ("dist/", tar::EntryType::Directory, b""),
+        ("dist/app.js", tar::EntryType::Regular, b"main()"),
+    ]);
+
+    let archive = DocsArchive::parse(&data).unwrap();
+
+    assert_eq!(
+        archive.files().collect::<Vec<_>>(),
+        vec![
+            &DocsFile {
+                path: "index.html".into(),
+                contents: b"<h1>my_package</h1>".to_vec(),
+            },
+            &DocsFile {
+                path: "dist/app.js".into(),
+                contents: b"main()".to_vec(),
+            },
+        ]
+    );
+    assert_eq!(archive.file("dist/app.js"), Some(b"main()".as_slice()));
+    assert_eq!(archive.file("missing.html"), None);
+}
+
+#[test]
+fn parse_rejects_path_traversal() {
+    for path in ["../secret", "/etc/passwd", "dist/../../secret"] {
+        let data = docs_tarball(&[(path, tar::EntryType::Regular, b"")]);
+        match DocsArchive::parse(&data) {
+            Err(Error::InvalidPath(p)) if p == path => (),
+            result => panic!("expected InvalidPath for {:?}, got {:?}", path, result),
+        }
+    }
+}
+
+#[test]
+fn parse_rejects_links() {
+    let data = docs_tarball(&[("index.html", tar::EntryType::Symlink, b"")]);
+    match DocsArchive::parse(&data) {
+        Err(Error::UnsupportedEntry(path)) if path == "index.html" => (),
+        result => panic!("expected UnsupportedEntry, got {:?}", result),
+    }
+}
+
+#[test]
+fn parse_not_gzipped() {
+    assert!(matches!(
+        DocsArchive::parse(b"not a tarball"),
+        Err(Error::Io(_))
+    ));
+}
+
+#[test]
+fn unpack_docs() {
+    let data = docs_tarball(&[
+        (
+            "index.html",
+            tar::EntryType::Regular,
+            b"<h1>my_package</h1>",
+        ),
+        ("dist/app.js", tar::EntryType::Regular, b"main()"),
+    ]);
+    let directory = std::env::temp_dir().join(format!("hexpm-docs-{}", std::process::id()));
+
+    DocsArchive::parse(&data)
+        .unwrap()
+        .unpack(&directory)
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read(directory.join("index.html")).unwrap(),
+        b"<h1>my_package</h1>"
+    );
+    assert_eq!(
+        std::fs::read(directory.join("dist/app.js")).unwrap(),
+        b"main()"
+    );
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn build_docs() {
+    let data = DocsTarballBuilder::new()
+        .add_file("./index.html", "<h1>my_package</h1>")
+        .add_file("dist/app.js", "main()")
+        .build()
+        .unwrap();
+
+    let archive = DocsArchive::parse(&data).unwrap();
+    assert_eq!(
+        archive
+            .files()
+            .map(|file| file.path.as_str())
+            .collect::<Vec<_>>(),
+        vec!["dist/app.js", "index.html"]
+    );
+    assert_eq!(
+        archive.file("index.html"),
+        Some(b"<h1>my_package</h1>".as_slice())
+    );
+}
+
+#[test]
+fn build_is_deterministic() {
+    let build = |files: &[(&str, &str)]| {
+        let mut builder = DocsTarballBuilder::new();
+        for (path, contents) in files {
+            let _ = builder.add_file(*path, *contents);
+        }
+        builder.build().unwrap()
+    };
+    assert_eq!(
+        build(&[("index.html", "index"), ("search.html", "search")]),
+        build(&[("search.html", "search"), ("index.html", "index")])
+    );
+}
+
+#[test]
+fn build_invalid_file_paths() {
+    for path in ["", "/index.html", "../index.html", "dist\\app.js"] {
+        let result = DocsTarballBuilder::new().add_file(path, "").build();
+        match result {
+            Err(Error::InvalidPath(p)) if p == path => (),
+            result => panic!("expected InvalidPath for {:?}, got {:?}", path, result),
+        }
+    }
+}
+
+#[test]
+fn build_too_large() {
+    // Pseudo-random bytes, which cannot be compressed below the limit
+    let mut state: u64 = 1;
+    let contents = (0..DOCS_MAX_COMPRESSED_SIZE + 1024)
+        .map(|_| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (state >> 56) as u8
+        })
+        .collect::<Vec<_>>();
+    let result = DocsTarballBuilder::new()
+        .add_file("index.html", contents)
+        .build();
+    assert!(matches!(result, Err(Error::TarballTooLarge)));
+}
+
+#[test]
+fn build_from_directory() {
+    let directory = std::env::temp_dir().join(format!("hexpm-docs-build-{}", std::process::id()));
+    std::fs::create_dir_all(directory.join("dist")).unwrap();
+    std::fs::write(directory.join("index.html"), "<h1>my_package</h1>").unwrap();
+    std::fs::write(directory.join("dist/app.js"), "main()").unwrap();
+
+    let from_directory = DocsTarballBuilder::new()
+        .add_directory(&directory)
+        .unwrap()
+        .build()
+        .unwrap();
+    std::fs::remove_dir_all(directory).unwrap();
+
+    let in_memory = DocsTarballBuilder::new()
+        .add_file("index.html", "<h1>my_package</h1>")
+        .add_file("dist/app.js", "main()")
+        .build()
+        .unwrap();
+    assert_eq!(from_directory, in_memory);
+}
@@ -3,9 +3,16 @@ mod proto;
 #[cfg(test)]
 mod tests;
 
+pub mod client;
+pub mod consult;
+pub mod docs;
+pub mod registry;
+pub mod resolver;
+pub mod tarball;
 pub mod version;
 
-use crate::proto::{signed::Signed, versions::Versions};
+use crate::proto::{names::Names, signed::Signed, versions::Versions};
+use base64::Engine;
 use bytes::buf::Buf;
 use flate2::read::GzDecoder;
 use http::{Method, StatusCode};
@@ -25,12 +32,30 @@ use thiserror::Error;
 use version::{Range, Version};
 use x509_parser::prelude::FromDer;
 
+/// The public key of the hex.pm repository, used to verify the resources
+/// served from https://repo.hex.pm/.
+pub const HEXPM_PUBLIC_KEY: &[u8] = b"-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEApqREcFDt5vV21JVe2QNB
+Edvzk6w36aNFhVGWN5toNJRjRJ6m4hIuG4KaXtDWVLjnvct6MYMfqhC79HAGwyF+
+IqR6Q6a5bbFSsImgBJwz1oadoVKD6ZNetAuCIK84cjMrEFRkELtEIPNHblCzUkkM
+3rS9+DPlnfG8hBvGi6tvQIuZmXGCxF/73hU0/MyGhbmEjIKRtG6b0sJYKelRLTPW
+XgK7s5pESgiwf2YC/2MGDXjAJfpfCd0RpLdvd4eRiXtVlE9qO9bND94E7PgQ/xqZ
+J1i2xWFndWa6nfFnRxZmCStCOZWYYPlaxr+FZceFbpMwzTNs4g3d4tLNUcbKAIH4
+0wIDAQAB
+-----END PUBLIC KEY-----
+";
+
 #[derive(Debug, Clone)]
 pub struct Config {
     /// Defaults to https://hex.pm/api/
     pub api_base: http::Uri,
     /// Defaults to https://repo.hex.pm/
     pub repository_base: http::Uri,
+    /// The organization whose private repository is used by package, release,
+    /// owner, and repository requests. Defaults to `None`, the public `hexpm`
+    /// repository. Set with [`Config::for_organization`] or
+    /// [`Config::set_organization`] so that the name is validated.
+    organization: Option<String>,
 }
 
 impl Config {
@@ -38,6 +63,62 @@ impl Config {
         Self {
             api_base: http::Uri::from_static("https://hex.pm/api/"),
             repository_base: http::Uri::from_static("https://repo.hex.pm/"),
+            organization: None,
+        }
+    }
+
+    /// A config for the private repository of an organization on hex.pm.
+    pub fn for_organization(organization: impl Into<String>) -> Result<Self, ApiError> {
+        let mut config = Self::new();
+        config.set_organization(Some(organization.into()))?;
+        Ok(config)
+    }
+
+    /// The organization whose private repository is in use, if any.
+    pub fn organization(&self) -> Option<&str> {
+        self.organization.as_deref()
+    }
+
+    /// Use the private repository of an organization, or the public `hexpm`
+    /// repository when `None`. The name is used within request paths, so is
+    /// rejected if it is not a valid organization name.
+    pub fn set_organization(&mut self, organization: Option<String>) -> Result<(), ApiError> {
+        if let Some(organization) = &organization
+            && !is_valid_package_name(organization)
+        {
+            return Err(ApiError::InvalidOrganizationNameFormat(
+                organization.clone(),
+            ));
+        }
+        self.organization = organization;
+        Ok(())
+    }
+
+    /// The name of the repository used, as found in
+    /// [`Dependency::repository`], i.e. `hexpm` or `hexpm:my_org`.
+    pub fn repository_name(&self) -> String {
+        match &self.organization {
+            Some(organization) => format!("hexpm:{}", organization),
+            None => "hexpm".into(),
+        }
+    }
+
+    /// Use the repository with the given name, as found in
+    /// [`Dependency::repository`], i.e. `hexpm` or `hexpm:my_org`. Other
+    /// repositories are not hosted by hex.pm, so are rejected.
+    pub fn set_repository_name(&mut self, repository: &str) -> Result<(), ApiError> {
+        match repository.split_once(':') {
+            None if repository == "hexpm" => self.set_organization(None),
+            Some(("hexpm", organization)) => self.set_organization(Some(organization.into())),
+            _ => Err(ApiError::UnsupportedRepository(repository.into())),
+        }
+    }
+
+    /// The path of a resource within the repository in use.
+    fn repository_path(&self, path_suffix: &str) -> String {
+        match &self.organization {
+            Some(organization) => format!("repos/{}/{}", organization, path_suffix),
+            None => path_suffix.to_string(),
         }
     }
 
@@ -52,13 +133,25 @@ impl Config {
             .header("accept", "application/json")
     }
 
+    /// An API request for a resource that belongs to a repository, such as a
+    /// package or release.
+    fn repository_api_request(
+        &self,
+        method: http::Method,
+        path_suffix: &str,
+        api_key: Option<&str>,
+    ) -> http::request::Builder {
+        self.api_request(method, &self.repository_path(path_suffix), api_key)
+    }
+
     fn repository_request(
         &self,
         method: http::Method,
         path_suffix: &str,
         api_key: Option<&str>,
     ) -> http::request::Builder {
-        make_request(self.repository_base.clone(), method, path_suffix, api_key)
+        let path = self.repository_path(path_suffix);
+        make_request(self.repository_base.clone(), method, &path, api_key)
     }
 }
 impl Default for Config {
@@ -101,6 +194,22 @@ fn make_request(
     builder
 }
 
+/// Requests that modify resources take the one-time password of users with two
+/// factor authentication enabled, which Hex requires for these requests.
+/// [`ApiError::OtpRequired`] is returned when it is missing.
+trait RequestBuilderExt {
+    fn otp(self, otp: Option<&str>) -> Self;
+}
+
+impl RequestBuilderExt for http::request::Builder {
+    fn otp(self, otp: Option<&str>) -> Self {
+        match otp {
+            Some(otp) => self.header("x-hex-otp", otp),
+            None => self,
+        }
+    }
+}
+
 /// Create a request that creates a Hex API key.
 ///
 /// API Docs:
@@ -112,18 +221,40 @@ pub fn api_create_api_key_request(
     username: &str,
     password: &str,
     key_name: &str,
+    otp: Option<&str>,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    api_create_api_key_with_permissions_request(
+        username,
+        password,
+        key_name,
+        &[KeyPermission::ApiWrite],
+        otp,
+        config,
+    )
+}
+
+/// Create a request that creates a Hex API key with the given permissions,
+/// rather than the `api:write` permission used by
+/// [`api_create_api_key_request`].
+///
+/// The response can be parsed with [`api_create_api_key_response`].
+pub fn api_create_api_key_with_permissions_request(
+    username: &str,
+    password: &str,
+    key_name: &str,
+    permissions: &[KeyPermission],
+    otp: Option<&str>,
     config: &Config,
 ) -> http::Request<Vec<u8>> {
     let body = json!({
         "name": key_name,
-        "permissions": [{
-            "domain": "api",
-            "resource": "write",
-        }],
+        "permissions": permissions,
     });
     let creds = http_auth_basic::Credentials::new(username, password).as_http_header();
     config
         .api_request(Method::POST, "keys", None)
+        .otp(otp)
         .header("authorization", creds)
         .body(body.to_string().into_bytes())
         .expect("create_api_key_request request")
@@ -138,9 +269,12 @@ pub fn api_create_api_key_response(response: http::Response<Vec<u8>>) -> Result<
     let (parts, body) = response.into_parts();
     match parts.status {
         StatusCode::CREATED => Ok(serde_json::from_slice::<Resp>(&body)?.secret),
-        StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited),
-        StatusCode::UNAUTHORIZED => Err(ApiError::InvalidCredentials),
-        status => Err(ApiError::unexpected_response(status, body)),
+        StatusCode::UNAUTHORIZED => Err(ApiError::unauthorized(
+            &parts,
+            HexErrorBody::parse(&body),
+            ApiError::InvalidCredentials,
+        )),
+        _ => Err(ApiError::from_response(&parts, body)),
     }
 }
 
@@ -154,14 +288,16 @@ pub fn api_create_api_key_response(response: http::Response<Vec<u8>>) -> Result<
 pub fn api_remove_api_key_request(
     name_of_key_to_delete: &str,
     api_key: &str,
+    otp: Option<&str>,
     config: &Config,
 ) -> http::Request<Vec<u8>> {
     config
         .api_request(
             Method::DELETE,
-            &format!("keys/{}", name_of_key_to_delete),
+            &format!("keys/{}", path_segment(name_of_key_to_delete)),
             Some(api_key),
         )
+        .otp(otp)
         .body(vec![])
         .expect("remove_api_key_request request")
 }
@@ -171,9 +307,169 @@ pub fn api_remove_api_key_response(response: http::Response<Vec<u8>>) -> Result<
     let (parts, body) = response.into_parts();
     match parts.status {
         StatusCode::NO_CONTENT | StatusCode::OK => Ok(()),
-        StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited),
-        StatusCode::UNAUTHORIZED => Err(ApiError::InvalidCredentials),
-        status => Err(ApiError::unexpected_response(status, body)),
+        StatusCode::UNAUTHORIZED => Err(ApiError::unauthorized(
+            &parts,
+            HexErrorBody::parse(&body),
+            ApiError::InvalidCredentials,
+        )),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// Create a request that lists the API keys of the authenticated user.
+///
+/// API Docs:
+///
+/// https://github.com/hexpm/hex/blob/main/lib/hex/api/key.ex
+pub fn api_list_api_keys_request(api_key: &str, config: &Config) -> http::Request<Vec<u8>> {
+    config
+        .api_request(Method::GET, "keys", Some(api_key))
+        .body(vec![])
+        .expect("list_api_keys request")
+}
+
+/// Parses a request that lists the API keys of the authenticated user.
+pub fn api_list_api_keys_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<Vec<ApiKey>, ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK => Ok(serde_json::from_slice(&body)?),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// Create a request that gets an API key of the authenticated user by name.
+pub fn api_get_api_key_request(
+    name: &str,
+    api_key: &str,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    config
+        .api_request(
+            Method::GET,
+            &format!("keys/{}", path_segment(name)),
+            Some(api_key),
+        )
+        .body(vec![])
+        .expect("get_api_key request")
+}
+
+/// Parses a request that gets an API key of the authenticated user.
+pub fn api_get_api_key_response(response: http::Response<Vec<u8>>) -> Result<ApiKey, ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK => Ok(serde_json::from_slice(&body)?),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// Create a request that deletes all of the API keys of the authenticated
+/// user, including the key used to authenticate.
+pub fn api_remove_all_api_keys_request(
+    api_key: &str,
+    otp: Option<&str>,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    config
+        .api_request(Method::DELETE, "keys", Some(api_key))
+        .otp(otp)
+        .body(vec![])
+        .expect("remove_all_api_keys request")
+}
+
+/// Parses a request that deleted all of the API keys of the authenticated
+/// user.
+pub fn api_remove_all_api_keys_response(response: http::Response<Vec<u8>>) -> Result<(), ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::NO_CONTENT | StatusCode::OK => Ok(()),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// An API key, as returned by the key listing endpoints. The secret of the
+/// key is only available when it is created.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct ApiKey {
+    pub name: String,
+    pub permissions: Vec<KeyPermission>,
+    /// Whether this is the key used to authenticate the request
+    #[serde(default)]
+    pub authing_key: bool,
+    pub revoked_at: Option<String>,
+    pub inserted_at: String,
+    pub updated_at: String,
+    pub last_use: Option<ApiKeyUse>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct ApiKeyUse {
+    pub used_at: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// A permission granted to an API key, made of a domain and an optional
+/// resource within that domain.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(from = "RawKeyPermission", into = "RawKeyPermission")]
+pub enum KeyPermission {
+    /// `api:read`, read access to the API.
+    ApiRead,
+    /// `api:write`, full access to the API.
+    ApiWrite,
+    /// `repository:<organization>`, access to the repository of an
+    /// organization.
+    Repository(String),
+    /// `repositories`, access to the repositories of all the organizations
+    /// the user belongs to.
+    Repositories,
+    /// `docs:<organization>`, access to the documentation of an organization.
+    Docs(String),
+    /// A permission in a domain not otherwise supported by this crate.
+    Other {
+        domain: String,
+        resource: Option<String>,
+    },
+}
+
+#[derive(serde::Deserialize, serde::Serialize)]
+struct RawKeyPermission {
+    domain: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    resource: Option<String>,
+}
+
+impl From<RawKeyPermission> for KeyPermission {
+    fn from(permission: RawKeyPermission) -> Self {
+        match (permission.domain.as_str(), permission.resource) {
+            ("api", Some(resource)) if resource == "read" => KeyPermission::ApiRead,
+            ("api", Some(resource)) if resource == "write" => KeyPermission::ApiWrite,
+            // An API permission without a resource grants full access.
+            ("api", None) => KeyPermission::ApiWrite,
+            ("repository", Some(organization)) => KeyPermission::Repository(organization),
+            ("repositories", None) => KeyPermission::Repositories,
+            ("docs", Some(organization)) => KeyPermission::Docs(organization),
+            (_, resource) => KeyPermission::Other {
+                domain: permission.domain,
+                resource,
+            },
+        }
+    }
+}
+
+impl From<KeyPermission> for RawKeyPermission {
+    fn from(permission: KeyPermission) -> Self {
+        let (domain, resource) = match permission {
+            KeyPermission::ApiRead => ("api".into(), Some("read".into())),
+            KeyPermission::ApiWrite => ("api".into(), Some("write".into())),
+            KeyPermission::Repository(organization) => ("repository".into(), Some(organization)),
+            KeyPermission::Repositories => ("repositories".into(), None),
+            KeyPermission::Docs(organization) => ("docs".into(), Some(organization)),
+            KeyPermission::Other { domain, resource } => (domain, resource),
+        };
+        Self { domain, resource }
     }
 }
 
@@ -190,6 +486,7 @@ pub fn api_retire_release_request(
     reason: RetirementReason,
     message: Option<&str>,
     api_key: &str,
+    otp: Option<&str>,
     config: &Config,
 ) -> http::Request<Vec<u8>> {
     let body = json!({
@@ -197,11 +494,12 @@ pub fn api_retire_release_request(
         "message": message,
     });
     config
-        .api_request(
+        .repository_api_request(
             Method::POST,
             &format!("packages/{}/releases/{}/retire", package, version),
             Some(api_key),
         )
+        .otp(otp)
         .body(body.to_string().into_bytes())
         .expect("retire_release_request request")
 }
@@ -211,9 +509,12 @@ pub fn api_retire_release_response(response: http::Response<Vec<u8>>) -> Result<
     let (parts, body) = response.into_parts();
     match parts.status {
         StatusCode::NO_CONTENT | StatusCode::OK => Ok(()),
-        StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited),
-        StatusCode::UNAUTHORIZED => Err(ApiError::InvalidCredentials),
-        status => Err(ApiError::unexpected_response(status, body)),
+        StatusCode::UNAUTHORIZED => Err(ApiError::unauthorized(
+            &parts,
+            HexErrorBody::parse(&body),
+            ApiError::InvalidCredentials,
+        )),
+        _ => Err(ApiError::from_response(&parts, body)),
     }
 }
 
@@ -228,14 +529,16 @@ pub fn api_unretire_release_request(
     package: &str,
     version: &str,
     api_key: &str,
+    otp: Option<&str>,
     config: &Config,
 ) -> http::Request<Vec<u8>> {
     config
-        .api_request(
+        .repository_api_request(
             Method::DELETE,
             &format!("packages/{}/releases/{}/retire", package, version),
             Some(api_key),
         )
+        .otp(otp)
         .body(vec![])
         .expect("unretire_release_request request")
 }
@@ -245,10 +548,169 @@ pub fn api_unretire_release_response(response: http::Response<Vec<u8>>) -> Resul
     let (parts, body) = response.into_parts();
     match parts.status {
         StatusCode::NO_CONTENT | StatusCode::OK => Ok(()),
-        StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited),
-        StatusCode::UNAUTHORIZED => Err(ApiError::InvalidCredentials),
-        status => Err(ApiError::unexpected_response(status, body)),
+        StatusCode::UNAUTHORIZED => Err(ApiError::unauthorized(
+            &parts,
+            HexErrorBody::parse(&body),
+            ApiError::InvalidCredentials,
+        )),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// Create a request that gets the PEM encoded public key used to sign the
+/// resources of the repository.
+///
+/// A key fetched this way should be checked with [`trust_public_key`] before
+/// it is used, as it is only as trustworthy as the connection it was fetched
+/// over.
+pub fn repository_get_public_key_request(
+    api_key: Option<&str>,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    config
+        .repository_request(Method::GET, "public_key", api_key)
+        .body(vec![])
+        .expect("get_public_key request")
+}
+
+/// Parse a response that gets the public key of the repository, checking it
+/// is a PEM encoded RSA public key.
+pub fn repository_get_public_key_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<Vec<u8>, ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK => {
+            let _ = public_key_fingerprint(&body)?;
+            Ok(body)
+        }
+        StatusCode::FORBIDDEN | StatusCode::NOT_FOUND => {
+            Err(ApiError::NotFound(HexErrorBody::parse(&body)))
+        }
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// The SHA-256 fingerprint of a PEM encoded RSA public key, as shown by
+/// `mix hex.repo show <repo> --public-key`, i.e.
+/// `SHA256:O1LOYhHFW4kcrblKAxROaDEzLD8bn1seWbe5tq8TRsk` for hex.pm.
+///
+/// This is the SSH fingerprint of the key: the unpadded base64 encoded
+/// SHA-256 digest of the key in the SSH public key format.
+pub fn public_key_fingerprint(pem_public_key: &[u8]) -> Result<String, ApiError> {
+    fn append(ssh_key: &mut Vec<u8>, bytes: &[u8]) {
+        ssh_key.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+        ssh_key.extend_from_slice(bytes);
+    }
+
+    // SSH integers are big endian with no leading zeros, other than one to
+    // keep the integer positive.
+    fn append_integer(ssh_key: &mut Vec<u8>, bytes: &[u8]) {
+        let start = bytes.iter().position(|b| *b != 0).unwrap_or(bytes.len());
+        let bytes = &bytes[start..];
+        let mut integer = Vec::with_capacity(bytes.len() + 1);
+        if bytes.first().is_some_and(|b| b & 0x80 != 0) {
+            integer.push(0);
+        }
+        integer.extend_from_slice(bytes);
+        append(ssh_key, &integer);
     }
+
+    let (_, pem) =
+        x509_parser::pem::parse_x509_pem(pem_public_key).map_err(|_| ApiError::InvalidPublicKey)?;
+    let (_, spki) = x509_parser::prelude::SubjectPublicKeyInfo::from_der(&pem.contents)
+        .map_err(|_| ApiError::InvalidPublicKey)?;
+    let Ok(x509_parser::public_key::PublicKey::RSA(key)) = spki.parsed() else {
+        return Err(ApiError::InvalidPublicKey);
+    };
+
+    let mut ssh_key = Vec::new();
+    append(&mut ssh_key, b"ssh-rsa");
+    append_integer(&mut ssh_key, key.exponent);
+    append_integer(&mut ssh_key, key.modulus);
+    let digest = ring::digest::digest(&SHA256, &ssh_key);
+    let encoded = base64::engine::general_purpose::STANDARD_NO_PAD.encode(digest.as_ref());
+    Ok(format!("SHA256:{}", encoded))
+}
+
+/// Trust a repository public key on first use. If a fingerprint has been
+/// pinned for the repository the key must match it, otherwise the key is
+/// trusted. Returns the fingerprint of the key, to be pinned if it was not
+/// already.
+///
+/// ```
+/// # fn fetch_public_key() -> Vec<u8> { hexpm::HEXPM_PUBLIC_KEY.to_vec() }
+/// let pinned = Some("SHA256:O1LOYhHFW4kcrblKAxROaDEzLD8bn1seWbe5tq8TRsk");
+/// let fingerprint = hexpm::trust_public_key(&fetch_public_key(), pinned).unwrap();
+/// assert_eq!(Some(fingerprint.as_str()), pinned);
+/// ```
+pub fn trust_public_key(
+    pem_public_key: &[u8],
+    pinned_fingerprint: Option<&str>,
+) -> Result<String, ApiError> {
+    let fingerprint = public_key_fingerprint(pem_public_key)?;
+    match pinned_fingerprint {
+        Some(pinned) if pinned.trim() != fingerprint => Err(ApiError::PublicKeyMismatch {
+            expected: pinned.trim().to_string(),
+            actual: fingerprint,
+        }),
+        _ => Ok(fingerprint),
+    }
+}
+
+/// Create a request that gets the names of all of the packages on the package
+/// registry.
+///
+/// https://github.com/hexpm/specifications/blob/main/registry-v2.md
+pub fn repository_v2_get_names_request(
+    api_key: Option<&str>,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    config
+        .repository_request(Method::GET, "names", api_key)
+        .header("accept", "application/json")
+        .body(vec![])
+        .expect("get_repository_names_request request")
+}
+
+/// Parse a request that gets the names of all of the packages on the package
+/// registry.
+pub fn repository_v2_get_names_response(
+    response: http::Response<Vec<u8>>,
+    public_key: &[u8],
+) -> Result<Vec<String>, ApiError> {
+    let (parts, body) = response.into_parts();
+
+    match parts.status {
+        StatusCode::OK => (),
+        _ => return Err(ApiError::from_response(&parts, body)),
+    };
+
+    let mut decoder = GzDecoder::new(body.reader());
+    let mut body = Vec::new();
+    decoder.read_to_end(&mut body)?;
+
+    repository_v2_get_names_body(&body, public_key)
+}
+
+/// Parse a signed binary message containing the names of all of the packages
+/// on the package registry.
+pub fn repository_v2_get_names_body(
+    protobuf_bytes: &Vec<u8>,
+    public_key: &[u8],
+) -> Result<Vec<String>, ApiError> {
+    let signed = Signed::decode(protobuf_bytes.as_slice())?;
+
+    let payload =
+        verify_payload(signed, public_key).map_err(|_| ApiError::IncorrectPayloadSignature)?;
+
+    let names = Names::decode(payload.as_slice())?
+        .packages
+        .into_iter()
+        .map(|package| package.name)
+        .collect();
+
+    Ok(names)
 }
 
 /// Create a request that get the names and versions of all of the packages on
@@ -278,7 +740,7 @@ pub fn repository_v2_get_versions_response(
 
     match parts.status {
         StatusCode::OK => (),
-        status => return Err(ApiError::unexpected_response(status, body)),
+        _ => return Err(ApiError::from_response(&parts, body)),
     };
 
     let mut decoder = GzDecoder::new(body.reader());
@@ -289,16 +751,58 @@ pub fn repository_v2_get_versions_response(
 }
 
 /// Parse a signed binary message containing all of the packages on the package registry.
+///
+/// Use [`repository_v2_get_registry_versions_body`] to also learn which versions
+/// have been retired.
 pub fn repository_v2_get_versions_body(
     protobuf_bytes: &Vec<u8>,
     public_key: &[u8],
 ) -> Result<HashMap<String, Vec<Version>>, ApiError> {
+    let versions = repository_v2_get_registry_versions_body(protobuf_bytes, public_key)?
+        .packages
+        .into_iter()
+        .map(|(name, versions)| {
+            let versions = versions.into_iter().map(|v| v.version).collect();
+            (name, versions)
+        })
+        .collect();
+
+    Ok(versions)
+}
+
+/// Parse a request that gets the names and versions of all of the packages on
+/// the package registry, including whether each version has been retired.
+pub fn repository_v2_get_registry_versions_response(
+    response: http::Response<Vec<u8>>,
+    public_key: &[u8],
+) -> Result<RegistryVersions, ApiError> {
+    let (parts, body) = response.into_parts();
+
+    match parts.status {
+        StatusCode::OK => (),
+        _ => return Err(ApiError::from_response(&parts, body)),
+    };
+
+    let mut decoder = GzDecoder::new(body.reader());
+    let mut body = Vec::new();
+    decoder.read_to_end(&mut body)?;
+
+    repository_v2_get_registry_versions_body(&body, public_key)
+}
+
+/// Parse a signed binary message containing all of the packages on the package
+/// registry, including whether each version has been retired.
+pub fn repository_v2_get_registry_versions_body(
+    protobuf_bytes: &Vec<u8>,
+    public_key: &[u8],
+) -> Result<RegistryVersions, ApiError> {
     let signed = Signed::decode(protobuf_bytes.as_slice())?;
 
     let payload =
         verify_payload(signed, public_key).map_err(|_| ApiError::IncorrectPayloadSignature)?;
 
-    let versions = Versions::decode(payload.as_slice())?
+    let versions = Versions::decode(payload.as_slice())?;
+    let packages = versions
         .packages
         .into_iter()
         .map(|n| {
@@ -309,13 +813,22 @@ pub fn repository_v2_get_versions_body(
             let versions = n
                 .versions
                 .iter()
-                .map(|v| parse_version(v.as_str()))
-                .collect::<Result<Vec<Version>, ApiError>>()?;
+                .enumerate()
+                .map(|(i, v)| {
+                    Ok(RegistryVersion {
+                        version: parse_version(v.as_str())?,
+                        retired: n.retired.contains(&(i as i32)),
+                    })
+                })
+                .collect::<Result<Vec<RegistryVersion>, ApiError>>()?;
             Ok((n.name, versions))
         })
         .collect::<Result<HashMap<_, _>, ApiError>>()?;
 
-    Ok(versions)
+    Ok(RegistryVersions {
+        repository: versions.repository,
+        packages,
+    })
 }
 
 /// Create a request to get the information for a package in the repository.
@@ -344,11 +857,10 @@ pub fn repository_v2_get_package_response(
 
     match parts.status {
         StatusCode::OK => (),
-        StatusCode::FORBIDDEN => return Err(ApiError::NotFound),
-        StatusCode::NOT_FOUND => return Err(ApiError::NotFound),
-        status => {
-            return Err(ApiError::unexpected_response(status, body));
+        StatusCode::FORBIDDEN | StatusCode::NOT_FOUND => {
+            return Err(ApiError::NotFound(HexErrorBody::parse(&body)));
         }
+        _ => return Err(ApiError::from_response(&parts, body)),
     };
 
     let mut decoder = GzDecoder::new(body.reader());
@@ -405,6 +917,7 @@ pub fn repository_get_package_tarball_request(
 
 /// Parse a response to download a version of a package as a tarball
 ///
+/// The tarball can be unpacked with [`tarball::ReleaseArchive::parse`].
 pub fn repository_get_package_tarball_response(
     response: http::Response<Vec<u8>>,
     checksum: &[u8],
@@ -412,16 +925,52 @@ pub fn repository_get_package_tarball_response(
     let (parts, body) = response.into_parts();
     match parts.status {
         StatusCode::OK => (),
-        StatusCode::FORBIDDEN => return Err(ApiError::NotFound),
-        StatusCode::NOT_FOUND => return Err(ApiError::NotFound),
-        status => {
-            return Err(ApiError::unexpected_response(status, body));
+        StatusCode::FORBIDDEN | StatusCode::NOT_FOUND => {
+            return Err(ApiError::NotFound(HexErrorBody::parse(&body)));
         }
+        _ => return Err(ApiError::from_response(&parts, body)),
     };
     let body = read_and_check_body(body.reader(), checksum)?;
     Ok(body)
 }
 
+/// Create a request to download the documentation of a version of a package
+/// as a gzipped tarball.
+pub fn repository_get_docs_tarball_request(
+    name: &str,
+    version: &str,
+    api_key: Option<&str>,
+    config: &Config,
+) -> Result<http::Request<Vec<u8>>, ApiError> {
+    validate_package_and_version(name, version)?;
+
+    Ok(config
+        .repository_request(
+            Method::GET,
+            &format!("docs/{}-{}.tar.gz", name, version),
+            api_key,
+        )
+        .header("accept", "application/gzip")
+        .body(vec![])
+        .expect("get_docs_tarball_request request"))
+}
+
+/// Parse a response to download the documentation of a version of a package.
+///
+/// The tarball can be unpacked with [`docs::DocsArchive::parse`].
+pub fn repository_get_docs_tarball_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<Vec<u8>, ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK => Ok(body),
+        StatusCode::FORBIDDEN | StatusCode::NOT_FOUND => {
+            Err(ApiError::NotFound(HexErrorBody::parse(&body)))
+        }
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
 /// API Docs:
 ///
 /// https://github.com/hexpm/hex/blob/main/lib/mix/tasks/hex.publish.ex#L384
@@ -431,16 +980,18 @@ pub fn api_remove_docs_request(
     package_name: &str,
     version: &str,
     api_key: &str,
+    otp: Option<&str>,
     config: &Config,
 ) -> Result<http::Request<Vec<u8>>, ApiError> {
     validate_package_and_version(package_name, version)?;
 
     Ok(config
-        .api_request(
+        .repository_api_request(
             Method::DELETE,
             &format!("packages/{}/releases/{}/docs", package_name, version),
             Some(api_key),
         )
+        .otp(otp)
         .body(vec![])
         .expect("remove_docs_request request"))
 }
@@ -449,11 +1000,7 @@ pub fn api_remove_docs_response(response: http::Response<Vec<u8>>) -> Result<(),
     let (parts, body) = response.into_parts();
     match parts.status {
         StatusCode::NO_CONTENT => Ok(()),
-        StatusCode::NOT_FOUND => Err(ApiError::NotFound),
-        StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited),
-        StatusCode::UNAUTHORIZED => Err(ApiError::InvalidApiKey),
-        StatusCode::FORBIDDEN => Err(ApiError::Forbidden),
-        status => Err(ApiError::unexpected_response(status, body)),
+        _ => Err(ApiError::from_response(&parts, body)),
     }
 }
 
@@ -467,16 +1014,18 @@ pub fn api_publish_docs_request(
     version: &str,
     gzipped_tarball: Vec<u8>,
     api_key: &str,
+    otp: Option<&str>,
     config: &Config,
 ) -> Result<http::Request<Vec<u8>>, ApiError> {
     validate_package_and_version(package_name, version)?;
 
     Ok(config
-        .api_request(
+        .repository_api_request(
             Method::POST,
             &format!("packages/{}/releases/{}/docs", package_name, version),
             Some(api_key),
         )
+        .otp(otp)
         .header("content-encoding", "x-gzip")
         .header("content-type", "application/x-tar")
         .body(gzipped_tarball)
@@ -487,11 +1036,7 @@ pub fn api_publish_docs_response(response: http::Response<Vec<u8>>) -> Result<()
     let (parts, body) = response.into_parts();
     match parts.status {
         StatusCode::CREATED => Ok(()),
-        StatusCode::NOT_FOUND => Err(ApiError::NotFound),
-        StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited),
-        StatusCode::UNAUTHORIZED => Err(ApiError::InvalidApiKey),
-        StatusCode::FORBIDDEN => Err(ApiError::Forbidden),
-        status => Err(ApiError::unexpected_response(status, body)),
+        _ => Err(ApiError::from_response(&parts, body)),
     }
 }
 
@@ -500,81 +1045,303 @@ pub fn api_publish_docs_response(response: http::Response<Vec<u8>>) -> Result<()
 /// https://github.com/hexpm/hex/blob/main/lib/mix/tasks/hex.publish.ex#L512
 ///
 /// https://github.com/hexpm/hex/blob/main/lib/hex/api/release.ex#L13
+///
+/// The release tarball can be created with [`tarball::TarballBuilder`].
 pub fn api_publish_package_request(
     release_tarball: Vec<u8>,
     api_key: &str,
+    otp: Option<&str>,
     config: &Config,
     replace: bool,
 ) -> http::Request<Vec<u8>> {
-    // TODO: do all the package tarball construction
     config
-        .api_request(
+        .repository_api_request(
             Method::POST,
             format!("publish?replace={}", replace).as_str(),
             Some(api_key),
         )
+        .otp(otp)
         .header("content-type", "application/x-tar")
         .body(release_tarball)
         .expect("publish_package_request request")
 }
 
-pub fn api_publish_package_response(response: http::Response<Vec<u8>>) -> Result<(), ApiError> {
-    // TODO: return data from body
+pub fn api_publish_package_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<PublishedRelease, ApiError> {
     let (parts, body) = response.into_parts();
     match parts.status {
-        StatusCode::OK | StatusCode::CREATED => Ok(()),
-        StatusCode::NOT_FOUND => Err(ApiError::NotFound),
-        StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited),
-        StatusCode::UNAUTHORIZED => Err(ApiError::InvalidApiKey),
-        StatusCode::FORBIDDEN => Err(ApiError::Forbidden),
-        StatusCode::UNPROCESSABLE_ENTITY => {
-            let body = &String::from_utf8_lossy(&body).to_string();
-            if body.contains("--replace") {
-                return Err(ApiError::NotReplacing);
-            }
-            Err(ApiError::LateModification)
-        }
-        status => Err(ApiError::unexpected_response(status, body)),
+        StatusCode::OK | StatusCode::CREATED => Ok(serde_json::from_slice(&body)?),
+        StatusCode::UNPROCESSABLE_ENTITY => Err(publish_validation_error(body)),
+        _ => Err(ApiError::from_response(&parts, body)),
     }
 }
 
-/// API Docs:
-///
-/// https://github.com/hexpm/hex/blob/main/lib/mix/tasks/hex.publish.ex#L371
-///
-/// https://github.com/hexpm/hex/blob/main/lib/hex/api/release.ex#L21
-pub fn api_revert_release_request(
-    package_name: &str,
-    version: &str,
-    api_key: &str,
-    config: &Config,
-) -> Result<http::Request<Vec<u8>>, ApiError> {
-    validate_package_and_version(package_name, version)?;
-
-    Ok(config
-        .api_request(
-            Method::DELETE,
-            &format!("packages/{}/releases/{}", package_name, version),
-            Some(api_key),
-        )
-        .body(vec![])
-        .expect("publish_package_request request"))
-}
+/// The validation error Hex gives on `inserted_at` when publishing a release
+/// that already exists without asking to replace it.
+const NOT_REPLACING_MESSAGE: &str = "must include the --replace flag to update an existing release";
+
+/// The validation error Hex gives on `inserted_at` when replacing a release
+/// more than an hour after it was published.
+const LATE_MODIFICATION_MESSAGE: &str =
+    "can only modify a release up to one hour after publication";
+
+/// Hex rejects a release that already exists and cannot be replaced with a
+/// validation error on `inserted_at`, and these are only told apart by their
+/// messages. Any other validation error, including one on `inserted_at` with
+/// a different message, is returned as [`ApiError::InvalidRelease`].
+fn publish_validation_error(body: Vec<u8>) -> ApiError {
+    let Some(error) = HexErrorBody::parse(&body) else {
+        return ApiError::unexpected_response(StatusCode::UNPROCESSABLE_ENTITY, body);
+    };
 
-pub fn api_revert_release_response(response: http::Response<Vec<u8>>) -> Result<(), ApiError> {
-    let (parts, body) = response.into_parts();
-    match parts.status {
-        StatusCode::NO_CONTENT => Ok(()),
-        StatusCode::NOT_FOUND => Err(ApiError::NotFound),
-        StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited),
-        StatusCode::UNAUTHORIZED => Err(ApiError::InvalidApiKey),
-        StatusCode::FORBIDDEN => Err(ApiError::Forbidden),
-        status => Err(ApiError::unexpected_response(status, body)),
+    let inserted_at = error
+        .errors
+        .iter()
+        .find(|e| e.field == "inserted_at")
+        .map(|e| e.message.as_str());
+    match inserted_at {
+        Some(NOT_REPLACING_MESSAGE) => ApiError::NotReplacing(error),
+        Some(LATE_MODIFICATION_MESSAGE) => ApiError::LateModification(error),
+        _ => ApiError::InvalidRelease(error),
+    }
+}
+
+/// A release that has been published to Hex.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct PublishedRelease {
+    #[serde(flatten)]
+    pub release: Release<ReleaseMeta>,
+    /// The API URL of the release
+    pub url: String,
+    /// The API URL of the package
+    pub package_url: Option<String>,
+    pub html_url: Option<String>,
+    pub docs_html_url: Option<String>,
+}
+
+/// An error document returned by the Hex API, i.e.
+///
+/// ```json
+/// {"status": 422, "message": "Validation error(s)", "errors": {"name": "has already been taken"}}
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct HexErrorBody {
+    pub status: Option<u16>,
+    pub message: String,
+    /// The problems with each field of the request, if any.
+    pub errors: Vec<ValidationError>,
+}
+
+impl HexErrorBody {
+    /// Parse the body of an error response. Returns `None` if the body is
+    /// not a Hex error document, as is the case for errors from the
+    /// repository.
+    pub fn parse(body: &[u8]) -> Option<Self> {
+        #[derive(Deserialize)]
+        struct Resp {
+            status: Option<u16>,
+            message: String,
+            #[serde(default)]
+            errors: serde_json::Value,
+        }
+        let resp: Resp = serde_json::from_slice(body).ok()?;
+        Some(Self {
+            status: resp.status,
+            message: resp.message,
+            errors: ValidationError::from_json(&resp.errors),
+        })
+    }
+}
+
+impl Display for HexErrorBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)?;
+        for (i, error) in self.errors.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { ", " };
+            write!(f, "{}{}", separator, error)?;
+        }
+        Ok(())
+    }
+}
+
+/// The rate limit of the Hex API for the client, as given by the
+/// `x-ratelimit-*` headers of an API response. Use [`with_rate_limit`] to get
+/// it along with the result of any response function.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RateLimit {
+    /// The number of requests permitted in the current window.
+    pub limit: u32,
+    /// The number of requests left in the current window.
+    pub remaining: u32,
+    /// When the current window ends, in seconds since the Unix epoch.
+    pub reset: u64,
+}
+
+impl RateLimit {
+    /// Read the rate limit from the headers of a response. Returns `None` if
+    /// any of the headers are missing or invalid, as is the case for
+    /// responses from the repository.
+    pub fn from_headers(headers: &http::HeaderMap) -> Option<Self> {
+        fn header<T: std::str::FromStr>(headers: &http::HeaderMap, name: &str) -> Option<T> {
+            headers.get(name)?.to_str().ok()?.trim().parse().ok()
+        }
+        Some(Self {
+            limit: header(headers, "x-ratelimit-limit")?,
+            remaining: header(headers, "x-ratelimit-remaining")?,
+            reset: header(headers, "x-ratelimit-reset")?,
+        })
+    }
+
+    /// Read the rate limit from a response, before it is given to the
+    /// response function that parses it. See [`with_rate_limit`] to do both.
+    pub fn from_response<T>(response: &http::Response<T>) -> Option<Self> {
+        Self::from_headers(response.headers())
+    }
+
+    /// When the current window ends and more requests can be made.
+    pub fn reset_at(&self) -> std::time::SystemTime {
+        std::time::UNIX_EPOCH + std::time::Duration::from_secs(self.reset)
+    }
+}
+
+/// Parse an API response with one of the response functions, also returning
+/// the rate limit that was reported with it so that clients can slow down
+/// before they are rate limited.
+///
+/// ```
+/// # let response = http::Response::builder()
+/// #     .status(204)
+/// #     .header("x-ratelimit-limit", "100")
+/// #     .header("x-ratelimit-remaining", "99")
+/// #     .header("x-ratelimit-reset", "1700000000")
+/// #     .body(vec![])
+/// #     .unwrap();
+/// let ((), rate_limit) =
+///     hexpm::with_rate_limit(response, hexpm::api_remove_api_key_response).unwrap();
+/// assert_eq!(rate_limit.unwrap().remaining, 99);
+/// ```
+///
+/// Response functions that take other arguments can be given as a closure,
+/// i.e. `|response| repository_v2_get_names_response(response, public_key)`.
+/// When the response is an error the rate limit is available from
+/// [`ApiError::rate_limit`] if the rate limit was exceeded.
+pub fn with_rate_limit<T>(
+    response: http::Response<Vec<u8>>,
+    parse: impl FnOnce(http::Response<Vec<u8>>) -> Result<T, ApiError>,
+) -> Result<(T, Option<RateLimit>), ApiError> {
+    let rate_limit = RateLimit::from_response(&response);
+    Ok((parse(response)?, rate_limit))
+}
+
+/// Read the `retry-after` header of a response, which is either a number of
+/// seconds or a date.
+fn retry_after(headers: &http::HeaderMap) -> Option<std::time::Duration> {
+    let value = headers
+        .get(http::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(std::time::Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(
+        date.duration_since(std::time::SystemTime::now())
+            .unwrap_or_default(),
+    )
+}
+
+/// A problem with a field of a request, as reported by Hex.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ValidationError {
+    /// The path of the field, with nested fields separated by dots, i.e.
+    /// `meta.description` or `requirements.gleam_stdlib`.
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationError {
+    /// Flatten the `errors` object of a Hex error response, which maps each
+    /// field to either a message or an object of errors for nested fields.
+    fn from_json(json: &serde_json::Value) -> Vec<Self> {
+        fn collect(field: String, value: &serde_json::Value, errors: &mut Vec<ValidationError>) {
+            match value {
+                serde_json::Value::Null => (),
+                serde_json::Value::String(message) => errors.push(ValidationError {
+                    field,
+                    message: message.clone(),
+                }),
+                serde_json::Value::Array(values) => {
+                    for value in values {
+                        collect(field.clone(), value, errors);
+                    }
+                }
+                serde_json::Value::Object(fields) => {
+                    for (name, value) in fields {
+                        let field = if field.is_empty() {
+                            name.clone()
+                        } else {
+                            format!("{}.{}", field, name)
+                        };
+                        collect(field, value, errors);
+                    }
+                }
+                value => errors.push(ValidationError {
+                    field,
+                    message: value.to_string(),
+                }),
+            }
+        }
+
+        let mut errors = Vec::new();
+        collect(String::new(), json, &mut errors);
+        errors
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// API Docs:
+///
+/// https://github.com/hexpm/hex/blob/main/lib/mix/tasks/hex.publish.ex#L371
+///
+/// https://github.com/hexpm/hex/blob/main/lib/hex/api/release.ex#L21
+pub fn api_revert_release_request(
+    package_name: &str,
+    version: &str,
+    api_key: &str,
+    otp: Option<&str>,
+    config: &Config,
+) -> Result<http::Request<Vec<u8>>, ApiError> {
+    validate_package_and_version(package_name, version)?;
+
+    Ok(config
+        .repository_api_request(
+            Method::DELETE,
+            &format!("packages/{}/releases/{}", package_name, version),
+            Some(api_key),
+        )
+        .otp(otp)
+        .body(vec![])
+        .expect("publish_package_request request"))
+}
+
+pub fn api_revert_release_response(response: http::Response<Vec<u8>>) -> Result<(), ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::NO_CONTENT => Ok(()),
+        _ => Err(ApiError::from_response(&parts, body)),
     }
 }
 
 /// See: https://github.com/hexpm/hex/blob/main/lib/mix/tasks/hex.owner.ex#L47
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum OwnerLevel {
     /// Has every package permission EXCEPT the ability to change who owns the package
     Maintainer,
@@ -591,6 +1358,46 @@ impl Display for OwnerLevel {
     }
 }
 
+/// Create a request that lists the owners of a package.
+///
+/// API Docs:
+///
+/// https://github.com/hexpm/hex/blob/main/lib/hex/api/package.ex
+pub fn api_list_owners_request(
+    package_name: &str,
+    api_key: Option<&str>,
+    config: &Config,
+) -> Result<http::Request<Vec<u8>>, ApiError> {
+    validate_package_name(package_name)?;
+
+    Ok(config
+        .repository_api_request(
+            Method::GET,
+            &format!("packages/{}/owners", package_name),
+            api_key,
+        )
+        .body(vec![])
+        .expect("list_owners_request request"))
+}
+
+/// Parses a request that lists the owners of a package.
+pub fn api_list_owners_response(response: http::Response<Vec<u8>>) -> Result<Vec<Owner>, ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK => Ok(serde_json::from_slice(&body)?),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// An owner of a package and their ownership level.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct Owner {
+    pub username: String,
+    /// Only present if the user has made their email address public
+    pub email: Option<String>,
+    pub level: OwnerLevel,
+}
+
 /// API Docs:
 ///
 /// https://github.com/hexpm/hex/blob/main/lib/mix/tasks/hex.owner.ex#L107
@@ -601,6 +1408,7 @@ pub fn api_add_owner_request(
     owner: &str,
     level: OwnerLevel,
     api_key: &str,
+    otp: Option<&str>,
     config: &Config,
 ) -> http::Request<Vec<u8>> {
     let body = json!({
@@ -609,11 +1417,12 @@ pub fn api_add_owner_request(
     });
 
     config
-        .api_request(
+        .repository_api_request(
             Method::PUT,
-            &format!("packages/{}/owners/{}", package_name, owner),
+            &format!("packages/{}/owners/{}", package_name, path_segment(owner)),
             Some(api_key),
         )
+        .otp(otp)
         .body(body.to_string().into_bytes())
         .expect("add_owner_request request")
 }
@@ -622,11 +1431,7 @@ pub fn api_add_owner_response(response: http::Response<Vec<u8>>) -> Result<(), A
     let (parts, body) = response.into_parts();
     match parts.status {
         StatusCode::NO_CONTENT => Ok(()),
-        StatusCode::NOT_FOUND => Err(ApiError::NotFound),
-        StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited),
-        StatusCode::UNAUTHORIZED => Err(ApiError::InvalidApiKey),
-        StatusCode::FORBIDDEN => Err(ApiError::Forbidden),
-        status => Err(ApiError::unexpected_response(status, body)),
+        _ => Err(ApiError::from_response(&parts, body)),
     }
 }
 
@@ -639,6 +1444,7 @@ pub fn api_transfer_owner_request(
     package_name: &str,
     owner: &str,
     api_key: &str,
+    otp: Option<&str>,
     config: &Config,
 ) -> http::Request<Vec<u8>> {
     let body = json!({
@@ -647,11 +1453,12 @@ pub fn api_transfer_owner_request(
     });
 
     config
-        .api_request(
+        .repository_api_request(
             Method::PUT,
-            &format!("packages/{}/owners/{}", package_name, owner),
+            &format!("packages/{}/owners/{}", package_name, path_segment(owner)),
             Some(api_key),
         )
+        .otp(otp)
         .body(body.to_string().into_bytes())
         .expect("transfer_owner_request request")
 }
@@ -660,11 +1467,7 @@ pub fn api_transfer_owner_response(response: http::Response<Vec<u8>>) -> Result<
     let (parts, body) = response.into_parts();
     match parts.status {
         StatusCode::NO_CONTENT => Ok(()),
-        StatusCode::NOT_FOUND => Err(ApiError::NotFound),
-        StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited),
-        StatusCode::UNAUTHORIZED => Err(ApiError::InvalidApiKey),
-        StatusCode::FORBIDDEN => Err(ApiError::Forbidden),
-        status => Err(ApiError::unexpected_response(status, body)),
+        _ => Err(ApiError::from_response(&parts, body)),
     }
 }
 
@@ -677,14 +1480,16 @@ pub fn api_remove_owner_request(
     package_name: &str,
     owner: &str,
     api_key: &str,
+    otp: Option<&str>,
     config: &Config,
 ) -> http::Request<Vec<u8>> {
     config
-        .api_request(
+        .repository_api_request(
             Method::DELETE,
-            &format!("packages/{}/owners/{}", package_name, owner),
+            &format!("packages/{}/owners/{}", package_name, path_segment(owner)),
             Some(api_key),
         )
+        .otp(otp)
         .body(vec![])
         .expect("remove_owner_request request")
 }
@@ -693,15 +1498,12 @@ pub fn api_remove_owner_response(response: http::Response<Vec<u8>>) -> Result<()
     let (parts, body) = response.into_parts();
     match parts.status {
         StatusCode::NO_CONTENT => Ok(()),
-        StatusCode::NOT_FOUND => Err(ApiError::NotFound),
-        StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited),
-        StatusCode::UNAUTHORIZED => Err(ApiError::InvalidApiKey),
-        StatusCode::FORBIDDEN => Err(ApiError::Forbidden),
-        status => Err(ApiError::unexpected_response(status, body)),
+        _ => Err(ApiError::from_response(&parts, body)),
     }
 }
 
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum ApiError {
     #[error(transparent)]
     Json(#[from] serde_json::Error),
@@ -709,11 +1511,19 @@ pub enum ApiError {
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
-    #[error("the rate limit for the Hex API has been exceeded for this IP")]
-    RateLimited,
+    #[error(
+        "the rate limit for the Hex API has been exceeded for this IP{}",
+        error_message(body)
+    )]
+    RateLimited {
+        rate_limit: Option<RateLimit>,
+        /// How long to wait before retrying, from the `retry-after` header.
+        retry_after: Option<std::time::Duration>,
+        body: Option<HexErrorBody>,
+    },
 
-    #[error("invalid username and password combination")]
-    InvalidCredentials,
+    #[error("invalid username and password combination{}", error_message(.0))]
+    InvalidCredentials(Option<HexErrorBody>),
 
     #[error("an unexpected response was sent by Hex: {0}: {1}")]
     UnexpectedResponse(StatusCode, String),
@@ -721,17 +1531,29 @@ pub enum ApiError {
     #[error("the given package name {0} is not valid")]
     InvalidPackageNameFormat(String),
 
+    #[error("the given organization name {0} is not valid")]
+    InvalidOrganizationNameFormat(String),
+
+    #[error("the repository {0} is not hosted by hex.pm")]
+    UnsupportedRepository(String),
+
     #[error("the payload signature does not match the downloaded payload")]
     IncorrectPayloadSignature,
 
+    #[error("the public key is not a PEM encoded RSA public key")]
+    InvalidPublicKey,
+
+    #[error("the public key has the fingerprint {actual}, but {expected} was expected")]
+    PublicKeyMismatch { expected: String, actual: String },
+
     #[error(transparent)]
     InvalidProtobuf(#[from] prost::DecodeError),
 
     #[error("unexpected version format {0}")]
     InvalidVersionFormat(String),
 
-    #[error("resource was not found")]
-    NotFound,
+    #[error("resource was not found{}", error_message(.0))]
+    NotFound(Option<HexErrorBody>),
 
     #[error("the version requirement format {0} is not valid")]
     InvalidVersionRequirementFormat(String),
@@ -739,17 +1561,50 @@ pub enum ApiError {
     #[error("the downloaded data did not have the expected checksum")]
     IncorrectChecksum,
 
-    #[error("the given API key was not valid")]
-    InvalidApiKey,
+    #[error("the given API key was not valid{}", error_message(.0))]
+    InvalidApiKey(Option<HexErrorBody>),
+
+    #[error("this account is not authorized for this action{}", error_message(.0))]
+    Forbidden(Option<HexErrorBody>),
 
-    #[error("this account is not authorized for this action")]
-    Forbidden,
+    #[error("a one-time password is required as this account has two factor authentication{}", error_message(.0))]
+    OtpRequired(Option<HexErrorBody>),
+
+    #[error("the given one-time password was not valid{}", error_message(.0))]
+    InvalidOtp(Option<HexErrorBody>),
 
     #[error("must explicitly express your intention to replace the release")]
-    NotReplacing,
+    NotReplacing(HexErrorBody),
 
     #[error("can only modify a release up to one hour after publication")]
-    LateModification,
+    LateModification(HexErrorBody),
+
+    #[error("the release is not valid: {0}")]
+    InvalidRelease(HexErrorBody),
+
+    #[error("the request is not valid: {0}")]
+    InvalidRequest(HexErrorBody),
+
+    #[error("an API key is required for this action")]
+    MissingApiKey,
+
+    #[error("OAuth error {error}{}", description.as_ref().map(|d| format!(": {}", d)).unwrap_or_default())]
+    OAuth {
+        error: String,
+        description: Option<String>,
+    },
+
+    #[error("the request could not be sent: {0}")]
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// The message of an error document sent by Hex, to follow the description of
+/// an error.
+fn error_message(body: &Option<HexErrorBody>) -> String {
+    match body {
+        Some(body) if !body.message.is_empty() => format!(": {}", body.message),
+        _ => String::new(),
+    }
 }
 
 impl ApiError {
@@ -757,11 +1612,90 @@ impl ApiError {
         ApiError::UnexpectedResponse(status, String::from_utf8_lossy(&body).to_string())
     }
 
+    /// The error for a response status that has no more specific meaning for
+    /// the request, along with the error document sent by Hex.
+    fn from_response(parts: &http::response::Parts, body: Vec<u8>) -> Self {
+        let error = HexErrorBody::parse(&body);
+        match (parts.status, error) {
+            (StatusCode::NOT_FOUND, error) => ApiError::NotFound(error),
+            (StatusCode::TOO_MANY_REQUESTS, body) => ApiError::RateLimited {
+                rate_limit: RateLimit::from_headers(&parts.headers),
+                retry_after: retry_after(&parts.headers),
+                body,
+            },
+            (StatusCode::UNAUTHORIZED, error) => {
+                ApiError::unauthorized(parts, error, ApiError::InvalidApiKey)
+            }
+            (StatusCode::FORBIDDEN, error) => ApiError::Forbidden(error),
+            (StatusCode::UNPROCESSABLE_ENTITY, Some(error)) => ApiError::InvalidRequest(error),
+            (status, _) => ApiError::unexpected_response(status, body),
+        }
+    }
+
+    /// Hex rejects requests from accounts with two factor authentication with
+    /// a `www-authenticate` header when the one-time password is missing or
+    /// wrong, otherwise the credentials themselves were not valid.
+    fn unauthorized(
+        parts: &http::response::Parts,
+        body: Option<HexErrorBody>,
+        invalid_credentials: fn(Option<HexErrorBody>) -> Self,
+    ) -> Self {
+        let challenge = parts
+            .headers
+            .get_all(http::header::WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if challenge.contains("error=\"totp_required\"") {
+            ApiError::OtpRequired(body)
+        } else if challenge.contains("error=\"invalid_totp\"") {
+            ApiError::InvalidOtp(body)
+        } else {
+            invalid_credentials(body)
+        }
+    }
+
+    /// The error document sent by Hex with the response, if there was one.
+    pub fn body(&self) -> Option<&HexErrorBody> {
+        match self {
+            ApiError::RateLimited { body, .. }
+            | ApiError::InvalidCredentials(body)
+            | ApiError::NotFound(body)
+            | ApiError::InvalidApiKey(body)
+            | ApiError::Forbidden(body)
+            | ApiError::OtpRequired(body)
+            | ApiError::InvalidOtp(body) => body.as_ref(),
+            ApiError::NotReplacing(body)
+            | ApiError::LateModification(body)
+            | ApiError::InvalidRelease(body)
+            | ApiError::InvalidRequest(body) => Some(body),
+            _ => None,
+        }
+    }
+
+    /// The rate limit reported with the response, if it was rate limited.
+    pub fn rate_limit(&self) -> Option<&RateLimit> {
+        match self {
+            ApiError::RateLimited { rate_limit, .. } => rate_limit.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// How long to wait before retrying a request that was rate limited, if
+    /// Hex sent a `retry-after` header.
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            ApiError::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
     /// Returns `true` if the api error is [`NotFound`].
     ///
     /// [`NotFound`]: ApiError::NotFound
     pub fn is_not_found(&self) -> bool {
-        matches!(self, Self::NotFound)
+        matches!(self, Self::NotFound(_))
     }
 
     pub fn is_invalid_protobuf(&self) -> bool {
@@ -842,6 +1776,7 @@ fn proto_to_release(release: proto::package::Release) -> Result<Release<()>, Api
         .expect("Failed to parse version format from Hex");
     Ok(Release {
         version,
+        inner_checksum: release.inner_checksum,
         outer_checksum: release.outer_checksum.unwrap_or_default(),
         retirement_status: proto_to_retirement_status(release.retired),
         requirements: dependencies,
@@ -849,6 +1784,34 @@ fn proto_to_release(release: proto::package::Release) -> Result<Release<()>, Api
     })
 }
 
+/// The versions of every package in a repository, as served by the registry
+/// `/versions` resource.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RegistryVersions {
+    pub repository: String,
+    pub packages: HashMap<String, Vec<RegistryVersion>>,
+}
+
+impl RegistryVersions {
+    /// The versions of a package that have not been retired.
+    pub fn unretired_versions(&self, package: &str) -> impl Iterator<Item = &Version> {
+        self.packages
+            .get(package)
+            .into_iter()
+            .flatten()
+            .filter(|v| !v.retired)
+            .map(|v| &v.version)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RegistryVersion {
+    pub version: Version,
+    /// Whether the release has been retired. The reason for retirement is only
+    /// available from the package resource.
+    pub retired: bool,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Package {
     pub name: String,
@@ -865,6 +1828,10 @@ pub struct Release<Meta> {
     /// If set the release is retired, a retired release should only be
     /// resolved if it has already been locked in a project
     pub retirement_status: Option<RetirementStatus>,
+    /// sha256 checksum of inner package tarball
+    /// deprecated in favor of outer_checksum, so may be empty
+    #[serde(default, deserialize_with = "deserialize_checksum")]
+    pub inner_checksum: Vec<u8>,
     /// sha256 checksum of outer package tarball
     /// required when encoding but optional when decoding
     #[serde(alias = "checksum", deserialize_with = "deserialize_checksum")]
@@ -953,14 +1920,44 @@ pub struct Dependency {
 
 static USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), " (", env!("CARGO_PKG_VERSION"), ")");
 
-fn validate_package_and_version(package: &str, version: &str) -> Result<(), ApiError> {
-    lazy_static! {
-        static ref PACKAGE_PATTERN: Regex = Regex::new(r"^[a-z]\w*$").unwrap();
-        static ref VERSION_PATTERN: Regex = Regex::new(r"^[a-zA-Z-0-9\._-]+$").unwrap();
-    }
-    if !PACKAGE_PATTERN.is_match(package) {
+lazy_static! {
+    static ref PACKAGE_PATTERN: Regex = Regex::new(r"^[a-z]\w*$").unwrap();
+    static ref VERSION_PATTERN: Regex = Regex::new(r"^[a-zA-Z-0-9\._-]+$").unwrap();
+}
+
+/// Characters that must be percent-encoded within a segment of a URL path.
+const PATH_SEGMENT: &percent_encoding::AsciiSet = &percent_encoding::CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Encode a user supplied value, such as the name of an API key, to be used as
+/// a single segment of a request path.
+fn path_segment(segment: &str) -> percent_encoding::PercentEncode<'_> {
+    percent_encoding::utf8_percent_encode(segment, PATH_SEGMENT)
+}
+
+fn is_valid_package_name(package: &str) -> bool {
+    PACKAGE_PATTERN.is_match(package)
+}
+
+fn validate_package_name(package: &str) -> Result<(), ApiError> {
+    if !is_valid_package_name(package) {
         return Err(ApiError::InvalidPackageNameFormat(package.to_string()));
     }
+    Ok(())
+}
+
+fn validate_package_and_version(package: &str, version: &str) -> Result<(), ApiError> {
+    validate_package_name(package)?;
     if !VERSION_PATTERN.is_match(version) {
         return Err(ApiError::InvalidVersionFormat(version.to_string()));
     }
@@ -1006,7 +2003,7 @@ pub fn api_get_package_release_request(
     config: &Config,
 ) -> http::Request<Vec<u8>> {
     config
-        .api_request(
+        .repository_api_request(
             Method::GET,
             &format!("packages/{}/releases/{}", name, version),
             api_key,
@@ -1025,10 +2022,876 @@ pub fn api_get_package_release_response(
 
     match parts.status {
         StatusCode::OK => Ok(serde_json::from_slice(&body)?),
-        StatusCode::NOT_FOUND => Err(ApiError::NotFound),
-        StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited),
-        StatusCode::UNAUTHORIZED => Err(ApiError::InvalidApiKey),
-        StatusCode::FORBIDDEN => Err(ApiError::Forbidden),
-        status => Err(ApiError::unexpected_response(status, body)),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// Create a request to get the information for a package, including its
+/// metadata, owners, download counts and releases.
+///
+/// API Docs:
+///
+/// https://github.com/hexpm/hex/blob/main/lib/hex/api/package.ex
+pub fn api_get_package_request(
+    name: &str,
+    api_key: Option<&str>,
+    config: &Config,
+) -> Result<http::Request<Vec<u8>>, ApiError> {
+    validate_package_name(name)?;
+
+    Ok(config
+        .repository_api_request(Method::GET, &format!("packages/{}", name), api_key)
+        .body(vec![])
+        .expect("get_package request"))
+}
+
+/// Parse a response to get the information for a package.
+pub fn api_get_package_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<PackageInfo, ApiError> {
+    let (parts, body) = response.into_parts();
+
+    match parts.status {
+        StatusCode::OK => Ok(serde_json::from_slice(&body)?),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// Information about a package from the Hex API.
+///
+/// Timestamps are ISO 8601 strings, as sent by Hex.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct PackageInfo {
+    pub name: String,
+    pub repository: String,
+    pub meta: PackageMeta,
+    #[serde(default)]
+    pub downloads: PackageDownloads,
+    #[serde(default)]
+    pub owners: Vec<PackageOwner>,
+    /// All releases of the package, newest first
+    pub releases: Vec<PackageRelease>,
+    /// The retirement status of each retired release, keyed by version
+    #[serde(default)]
+    pub retirements: HashMap<String, RetirementStatus>,
+    pub latest_version: Option<Version>,
+    /// The newest release that is not a pre-release
+    pub latest_stable_version: Option<Version>,
+    pub html_url: Option<String>,
+    pub docs_html_url: Option<String>,
+    pub inserted_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default, serde::Deserialize)]
+pub struct PackageMeta {
+    pub description: Option<String>,
+    #[serde(default)]
+    pub licenses: Vec<String>,
+    #[serde(default)]
+    pub links: HashMap<String, String>,
+    #[serde(default)]
+    pub maintainers: Vec<String>,
+}
+
+/// Download counts of a package. Counts are absent for packages that have
+/// not yet been downloaded.
+#[derive(Debug, PartialEq, Eq, Clone, Default, serde::Deserialize)]
+pub struct PackageDownloads {
+    #[serde(default)]
+    pub all: u64,
+    /// Downloads in the last 90 days
+    #[serde(default)]
+    pub recent: u64,
+    #[serde(default)]
+    pub week: u64,
+    #[serde(default)]
+    pub day: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct PackageOwner {
+    pub username: String,
+    pub email: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct PackageRelease {
+    pub version: Version,
+    /// The API URL of the release
+    pub url: String,
+    #[serde(default)]
+    pub has_docs: bool,
+    pub inserted_at: String,
+}
+
+/// The query for a package search, built up with the setter methods.
+///
+/// ```
+/// use hexpm::{PackageSearch, PackageSort};
+///
+/// let mut query = PackageSearch::new();
+/// query.search("json").sort(PackageSort::RecentDownloads).page(2);
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct PackageSearch {
+    search: Option<String>,
+    sort: Option<PackageSort>,
+    page: Option<u32>,
+}
+
+impl PackageSearch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Search terms, using the Hex search syntax, i.e. `json` or
+    /// `description:json`.
+    pub fn search(&mut self, terms: impl Into<String>) -> &mut Self {
+        self.search = Some(terms.into());
+        self
+    }
+
+    pub fn sort(&mut self, sort: PackageSort) -> &mut Self {
+        self.sort = Some(sort);
+        self
+    }
+
+    /// The page of results to get, starting from 1.
+    pub fn page(&mut self, page: u32) -> &mut Self {
+        self.page = Some(page);
+        self
+    }
+
+    fn to_query_string(&self) -> String {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        if let Some(search) = &self.search {
+            let _ = query.append_pair("search", search);
+        }
+        if let Some(sort) = &self.sort {
+            let _ = query.append_pair("sort", sort.to_str());
+        }
+        if let Some(page) = self.page {
+            let _ = query.append_pair("page", &page.to_string());
+        }
+        query.finish()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PackageSort {
+    Name,
+    RecentDownloads,
+    TotalDownloads,
+    InsertedAt,
+    UpdatedAt,
+}
+
+impl PackageSort {
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            PackageSort::Name => "name",
+            PackageSort::RecentDownloads => "recent_downloads",
+            PackageSort::TotalDownloads => "total_downloads",
+            PackageSort::InsertedAt => "inserted_at",
+            PackageSort::UpdatedAt => "updated_at",
+        }
+    }
+}
+
+/// Create a request to search for packages.
+///
+/// API Docs:
+///
+/// https://github.com/hexpm/hex/blob/main/lib/hex/api/package.ex
+pub fn api_search_packages_request(
+    query: &PackageSearch,
+    api_key: Option<&str>,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    let query = query.to_query_string();
+    let path = if query.is_empty() {
+        "packages".to_string()
+    } else {
+        format!("packages?{}", query)
+    };
+    config
+        .repository_api_request(Method::GET, &path, api_key)
+        .body(vec![])
+        .expect("search_packages request")
+}
+
+/// Parse a response to search for packages.
+pub fn api_search_packages_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<PackageSearchPage, ApiError> {
+    let (parts, body) = response.into_parts();
+
+    match parts.status {
+        StatusCode::OK => Ok(PackageSearchPage {
+            packages: serde_json::from_slice(&body)?,
+            pagination: Pagination::from_headers(&parts.headers),
+        }),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// A page of package search results.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PackageSearchPage {
+    pub packages: Vec<PackageInfo>,
+    pub pagination: Pagination,
+}
+
+/// The page numbers of the pages linked to from a paginated response, as
+/// given by its `Link` header.
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Pagination {
+    pub first: Option<u32>,
+    pub prev: Option<u32>,
+    pub next: Option<u32>,
+    pub last: Option<u32>,
+}
+
+impl Pagination {
+    /// Read the pages from `Link` headers such as
+    /// `<https://hex.pm/api/packages?page=2>; rel="next"`. Links without a
+    /// `page` query parameter are ignored.
+    pub fn from_headers(headers: &http::HeaderMap) -> Self {
+        let mut pagination = Self::default();
+        let links = headers
+            .get_all(http::header::LINK)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+
+        for link in links {
+            let mut parts = link.split(';').map(str::trim);
+            let Some(page) = parts
+                .next()
+                .and_then(|uri| uri.strip_prefix('<')?.strip_suffix('>'))
+                .and_then(|uri| url::Url::parse(uri).ok())
+                .and_then(|uri| {
+                    uri.query_pairs()
+                        .find(|(key, _)| key == "page")
+                        .and_then(|(_, page)| page.parse().ok())
+                })
+            else {
+                continue;
+            };
+            for rel in parts.filter_map(|param| param.strip_prefix("rel=")) {
+                for rel in rel.trim_matches('"').split_whitespace() {
+                    match rel {
+                        "first" => pagination.first = Some(page),
+                        "prev" => pagination.prev = Some(page),
+                        "next" => pagination.next = Some(page),
+                        "last" => pagination.last = Some(page),
+                        _ => (),
+                    }
+                }
+            }
+        }
+        pagination
+    }
+}
+
+/// Create a request to get the user that the API key belongs to.
+///
+/// API Docs:
+///
+/// https://github.com/hexpm/hex/blob/main/lib/hex/api/user.ex
+pub fn api_get_me_request(api_key: &str, config: &Config) -> http::Request<Vec<u8>> {
+    config
+        .api_request(Method::GET, "users/me", Some(api_key))
+        .body(vec![])
+        .expect("get_me request")
+}
+
+/// Parse a response to get the user that the API key belongs to.
+pub fn api_get_me_response(response: http::Response<Vec<u8>>) -> Result<User, ApiError> {
+    api_get_user_response(response)
+}
+
+/// Create a request to get a user by their username or email address.
+pub fn api_get_user_request(
+    username: &str,
+    api_key: Option<&str>,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    config
+        .api_request(
+            Method::GET,
+            &format!("users/{}", path_segment(username)),
+            api_key,
+        )
+        .body(vec![])
+        .expect("get_user request")
+}
+
+/// Parse a response to get a user.
+pub fn api_get_user_response(response: http::Response<Vec<u8>>) -> Result<User, ApiError> {
+    let (parts, body) = response.into_parts();
+
+    match parts.status {
+        StatusCode::OK => Ok(serde_json::from_slice(&body)?),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// Create a request to get the packages owned by a user.
+pub fn api_get_user_packages_request(
+    username: &str,
+    api_key: Option<&str>,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    config
+        .api_request(
+            Method::GET,
+            &format!("users/{}/packages", path_segment(username)),
+            api_key,
+        )
+        .body(vec![])
+        .expect("get_user_packages request")
+}
+
+/// Parse a response to get the packages owned by a user.
+pub fn api_get_user_packages_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<Vec<UserPackage>, ApiError> {
+    let (parts, body) = response.into_parts();
+
+    match parts.status {
+        StatusCode::OK => Ok(serde_json::from_slice(&body)?),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct User {
+    pub username: String,
+    /// Only present for the current user, or if the user has made their email
+    /// address public
+    pub email: Option<String>,
+    pub full_name: Option<String>,
+    /// The organizations the user is a member of. Only present for the
+    /// current user.
+    #[serde(default)]
+    pub organizations: Vec<UserOrganization>,
+    /// The packages the user owns
+    #[serde(default)]
+    pub packages: Vec<UserPackage>,
+    pub inserted_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct UserOrganization {
+    pub name: String,
+    pub role: OrganizationRole,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct UserPackage {
+    pub name: String,
+    pub repository: String,
+    /// The API URL of the package
+    pub url: Option<String>,
+    pub html_url: Option<String>,
+}
+
+/// The role of a member of an organization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrganizationRole {
+    /// Can access the packages of the organization
+    Read,
+    /// Can also publish and manage the packages of the organization
+    Write,
+    /// Can also manage the members and billing of the organization
+    Admin,
+}
+
+impl Display for OrganizationRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrganizationRole::Read => write!(f, "read"),
+            OrganizationRole::Write => write!(f, "write"),
+            OrganizationRole::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+/// Create a request to get an organization.
+///
+/// API Docs:
+///
+/// https://github.com/hexpm/hex/blob/main/lib/hex/api/organization.ex
+pub fn api_get_organization_request(
+    organization: &str,
+    api_key: &str,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    config
+        .api_request(
+            Method::GET,
+            &format!("orgs/{}", path_segment(organization)),
+            Some(api_key),
+        )
+        .body(vec![])
+        .expect("get_organization request")
+}
+
+/// Parse a response to get an organization.
+pub fn api_get_organization_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<Organization, ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK => Ok(serde_json::from_slice(&body)?),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// Create a request to list the members of an organization.
+pub fn api_list_organization_members_request(
+    organization: &str,
+    api_key: &str,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    config
+        .api_request(
+            Method::GET,
+            &format!("orgs/{}/members", path_segment(organization)),
+            Some(api_key),
+        )
+        .body(vec![])
+        .expect("list_organization_members request")
+}
+
+/// Parse a response to list the members of an organization.
+pub fn api_list_organization_members_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<Vec<OrganizationMember>, ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK => Ok(serde_json::from_slice(&body)?),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// Create a request to get a member of an organization.
+pub fn api_get_organization_member_request(
+    organization: &str,
+    username: &str,
+    api_key: &str,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    config
+        .api_request(
+            Method::GET,
+            &format!(
+                "orgs/{}/members/{}",
+                path_segment(organization),
+                path_segment(username)
+            ),
+            Some(api_key),
+        )
+        .body(vec![])
+        .expect("get_organization_member request")
+}
+
+/// Parse a response to get a member of an organization.
+pub fn api_get_organization_member_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<OrganizationMember, ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK => Ok(serde_json::from_slice(&body)?),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// Create a request to add a user to an organization with the given role.
+pub fn api_add_organization_member_request(
+    organization: &str,
+    username: &str,
+    role: OrganizationRole,
+    api_key: &str,
+    otp: Option<&str>,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    let body = json!({
+        "name": username,
+        "role": role.to_string(),
+    });
+
+    config
+        .api_request(
+            Method::POST,
+            &format!("orgs/{}/members", path_segment(organization)),
+            Some(api_key),
+        )
+        .otp(otp)
+        .body(body.to_string().into_bytes())
+        .expect("add_organization_member request")
+}
+
+/// Parse a response to add a user to an organization.
+pub fn api_add_organization_member_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<(), ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK | StatusCode::CREATED | StatusCode::NO_CONTENT => Ok(()),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// Create a request to change the role of a member of an organization.
+pub fn api_update_organization_member_request(
+    organization: &str,
+    username: &str,
+    role: OrganizationRole,
+    api_key: &str,
+    otp: Option<&str>,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    let body = json!({
+        "role": role.to_string(),
+    });
+
+    config
+        .api_request(
+            Method::PATCH,
+            &format!(
+                "orgs/{}/members/{}",
+                path_segment(organization),
+                path_segment(username)
+            ),
+            Some(api_key),
+        )
+        .otp(otp)
+        .body(body.to_string().into_bytes())
+        .expect("update_organization_member request")
+}
+
+/// Parse a response to change the role of a member of an organization.
+pub fn api_update_organization_member_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<(), ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK | StatusCode::NO_CONTENT => Ok(()),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// Create a request to remove a member from an organization.
+pub fn api_remove_organization_member_request(
+    organization: &str,
+    username: &str,
+    api_key: &str,
+    otp: Option<&str>,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    config
+        .api_request(
+            Method::DELETE,
+            &format!(
+                "orgs/{}/members/{}",
+                path_segment(organization),
+                path_segment(username)
+            ),
+            Some(api_key),
+        )
+        .otp(otp)
+        .body(vec![])
+        .expect("remove_organization_member request")
+}
+
+/// Parse a response to remove a member from an organization.
+pub fn api_remove_organization_member_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<(), ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::NO_CONTENT | StatusCode::OK => Ok(()),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+/// Create a request to get a page of the audit log of an organization, newest
+/// entries first. Pages start from 1.
+pub fn api_get_organization_audit_logs_request(
+    organization: &str,
+    page: u32,
+    api_key: &str,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    config
+        .api_request(
+            Method::GET,
+            &format!(
+                "orgs/{}/audit-logs?page={}",
+                path_segment(organization),
+                page
+            ),
+            Some(api_key),
+        )
+        .body(vec![])
+        .expect("get_organization_audit_logs request")
+}
+
+/// Parse a response to get a page of the audit log of an organization.
+pub fn api_get_organization_audit_logs_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<Vec<AuditLog>, ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK => Ok(serde_json::from_slice(&body)?),
+        _ => Err(ApiError::from_response(&parts, body)),
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct Organization {
+    pub name: String,
+    #[serde(default)]
+    pub billing_active: bool,
+    pub inserted_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct OrganizationMember {
+    pub username: String,
+    pub email: Option<String>,
+    pub role: OrganizationRole,
+}
+
+/// An action taken within an organization.
+#[derive(Debug, PartialEq, Clone, serde::Deserialize)]
+pub struct AuditLog {
+    /// The kind of action, i.e. `release.publish` or `organization.member.add`
+    pub action: String,
+    /// Details of the action, which vary by the kind of action
+    #[serde(default)]
+    pub params: serde_json::Value,
+    pub user_agent: Option<String>,
+    pub inserted_at: String,
+    /// The user who took the action, if it was taken by a user
+    pub user: Option<AuditLogUser>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct AuditLogUser {
+    pub username: String,
+}
+
+/// The OAuth grant type for exchanging a device code for a token.
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Create a request that starts the OAuth device authorization flow, the
+/// recommended way of logging in to Hex as it works for accounts with two
+/// factor authentication. Scopes are separated by spaces, i.e.
+/// `api repositories`.
+///
+/// The user approves the request by visiting the verification URI of the
+/// returned [`DeviceAuthorization`] and entering its user code, while the
+/// device code is used to poll for the token with
+/// [`api_oauth_device_token_request`].
+pub fn api_oauth_device_authorization_request(
+    client_id: &str,
+    scope: &str,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    let body = json!({
+        "client_id": client_id,
+        "scope": scope,
+    });
+    config
+        .api_request(Method::POST, "oauth/device_authorization", None)
+        .body(body.to_string().into_bytes())
+        .expect("oauth_device_authorization request")
+}
+
+/// Parse a response that starts the OAuth device authorization flow.
+pub fn api_oauth_device_authorization_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<DeviceAuthorization, ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK => Ok(serde_json::from_slice(&body)?),
+        _ => Err(oauth_error(&parts, body)),
+    }
+}
+
+/// Create a request that polls for the token of an OAuth device
+/// authorization. See [`api_oauth_device_authorization_request`].
+pub fn api_oauth_device_token_request(
+    client_id: &str,
+    device_code: &str,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    let body = json!({
+        "grant_type": DEVICE_CODE_GRANT_TYPE,
+        "client_id": client_id,
+        "device_code": device_code,
+    });
+    config
+        .api_request(Method::POST, "oauth/token", None)
+        .body(body.to_string().into_bytes())
+        .expect("oauth_device_token request")
+}
+
+/// Parse a response that polls for the token of an OAuth device
+/// authorization. Once the device code expires or the user denies the request
+/// an [`ApiError::OAuth`] is returned.
+pub fn api_oauth_device_token_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<DeviceTokenPoll, ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK => Ok(DeviceTokenPoll::Authorized(serde_json::from_slice(&body)?)),
+        _ => match oauth_error(&parts, body) {
+            ApiError::OAuth { error, .. } if error == "authorization_pending" => {
+                Ok(DeviceTokenPoll::Pending)
+            }
+            ApiError::OAuth { error, .. } if error == "slow_down" => Ok(DeviceTokenPoll::SlowDown),
+            error => Err(error),
+        },
+    }
+}
+
+/// Create a request that exchanges the refresh token of an [`OAuthToken`] for
+/// a new token.
+pub fn api_oauth_refresh_token_request(
+    client_id: &str,
+    refresh_token: &str,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    let body = json!({
+        "grant_type": "refresh_token",
+        "client_id": client_id,
+        "refresh_token": refresh_token,
+    });
+    config
+        .api_request(Method::POST, "oauth/token", None)
+        .body(body.to_string().into_bytes())
+        .expect("oauth_refresh_token request")
+}
+
+/// Parse a response that refreshes an OAuth token.
+pub fn api_oauth_refresh_token_response(
+    response: http::Response<Vec<u8>>,
+) -> Result<OAuthToken, ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK => Ok(serde_json::from_slice(&body)?),
+        _ => Err(oauth_error(&parts, body)),
+    }
+}
+
+/// Create a request that revokes an OAuth access or refresh token, i.e. when
+/// logging out.
+pub fn api_oauth_revoke_token_request(
+    client_id: &str,
+    token: &str,
+    config: &Config,
+) -> http::Request<Vec<u8>> {
+    let body = json!({
+        "client_id": client_id,
+        "token": token,
+    });
+    config
+        .api_request(Method::POST, "oauth/revoke", None)
+        .body(body.to_string().into_bytes())
+        .expect("oauth_revoke_token request")
+}
+
+/// Parse a response that revokes an OAuth token. Revoking a token that is not
+/// valid succeeds.
+pub fn api_oauth_revoke_token_response(response: http::Response<Vec<u8>>) -> Result<(), ApiError> {
+    let (parts, body) = response.into_parts();
+    match parts.status {
+        StatusCode::OK | StatusCode::NO_CONTENT => Ok(()),
+        _ => Err(oauth_error(&parts, body)),
+    }
+}
+
+/// OAuth endpoints report errors with an `error` code rather than a Hex error
+/// document.
+fn oauth_error(parts: &http::response::Parts, body: Vec<u8>) -> ApiError {
+    #[derive(Deserialize)]
+    struct Resp {
+        error: String,
+        error_description: Option<String>,
+    }
+    match serde_json::from_slice(&body) {
+        Ok(Resp {
+            error,
+            error_description,
+        }) => ApiError::OAuth {
+            error,
+            description: error_description,
+        },
+        Err(_) => ApiError::from_response(parts, body),
+    }
+}
+
+/// A pending OAuth device authorization.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct DeviceAuthorization {
+    /// The code used to poll for the token. This is not shown to the user.
+    pub device_code: String,
+    /// The code the user enters at the verification URI.
+    pub user_code: String,
+    pub verification_uri: String,
+    /// The verification URI with the user code already filled in.
+    pub verification_uri_complete: Option<String>,
+    /// The number of seconds until the device code expires.
+    pub expires_in: u64,
+    /// The number of seconds to wait between polls for the token.
+    #[serde(default = "default_poll_interval")]
+    pub interval: u64,
+}
+
+fn default_poll_interval() -> u64 {
+    5
+}
+
+/// The state of an OAuth device authorization, as returned when polling for
+/// its token.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DeviceTokenPoll {
+    /// The user has not yet approved the authorization. Poll again after the
+    /// interval of the [`DeviceAuthorization`].
+    Pending,
+    /// Polling is too frequent. Poll again after increasing the interval by 5
+    /// seconds.
+    SlowDown,
+    Authorized(OAuthToken),
+}
+
+/// An OAuth token for the Hex API.
+#[derive(Debug, PartialEq, Eq, Clone, serde::Deserialize)]
+pub struct OAuthToken {
+    pub access_token: String,
+    /// Used to get a new token once this one expires, with
+    /// [`api_oauth_refresh_token_request`].
+    pub refresh_token: Option<String>,
+    pub token_type: String,
+    /// The number of seconds until the access token expires.
+    pub expires_in: Option<u64>,
+    pub scope: Option<String>,
+}
+
+impl OAuthToken {
+    /// The `authorization` header value for the token, which can be used as
+    /// the API key of any request, or as the `api_key` of a
+    /// [`Client`](crate::client::Client).
+    pub fn authorization(&self) -> String {
+        format!("Bearer {}", self.access_token)
     }
 }
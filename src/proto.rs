@@ -1,5 +1,6 @@
 #![allow(clippy::enum_variant_names)]
 
+pub mod names;
 pub mod package;
 pub mod signed;
 pub mod versions;
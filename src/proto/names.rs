@@ -0,0 +1,19 @@
+// This file is @generated by prost-build.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Names {
+    /// All packages in the repository
+    #[prost(message, repeated, tag = "1")]
+    pub packages: ::prost::alloc::vec::Vec<Package>,
+    /// Name of repository
+    #[prost(string, required, tag = "2")]
+    pub repository: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Package {
+    /// Package name
+    ///
+    /// If set, the name of the package repository (NEVER USED, DEPRECATED)
+    /// string repository = 2;
+    #[prost(string, required, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+}
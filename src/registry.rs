@@ -0,0 +1,240 @@
+//! Encoding and signing of the Hex registry v2 resources, for serving a
+//! Hex-compatible repository. The output of each function can be read by the
+//! corresponding `repository_v2_*` parsing functions at the crate root.
+//!
+//! Each resource is a protobuf message wrapped in a `Signed` message, signed
+//! with the repository's RSA private key using SHA-512 PKCS#1 v1.5, and
+//! gzipped.
+//!
+//! A complete static repository can be built from a directory of package
+//! tarballs with [`RegistryBuilder`].
+//!
+//! https://github.com/hexpm/specifications/blob/main/registry-v2.md
+
+mod builder;
+#[cfg(test)]
+mod tests;
+
+use std::io::Write;
+
+use base64::Engine;
+use flate2::{Compression, write::GzEncoder};
+use prost::Message;
+use ring::{
+    rand::SystemRandom,
+    signature::{RSA_PKCS1_SHA512, RsaKeyPair},
+};
+use thiserror::Error;
+
+use crate::proto::{self, names::Names, signed::Signed, versions::Versions};
+use crate::{Package, RegistryVersions, Release, RetirementReason};
+
+pub use self::builder::{Registry, RegistryBuilder};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("the private key is not a PEM encoded RSA private key")]
+    InvalidPrivateKey,
+
+    #[error("the payload could not be signed")]
+    Signing,
+
+    #[error(transparent)]
+    Tarball(#[from] crate::tarball::Error),
+
+    #[error("the tarball {0} should be named {1}")]
+    UnexpectedTarballName(String, String),
+
+    #[error("the release {0} {1} has been added more than once")]
+    DuplicateRelease(String, String),
+}
+
+/// An RSA private key used to sign registry resources.
+///
+/// Both PKCS#1 (`BEGIN RSA PRIVATE KEY`) and PKCS#8 (`BEGIN PRIVATE KEY`) PEM
+/// encoded keys are accepted, as created by
+/// `openssl genrsa -out private_key.pem 2048`.
+#[derive(Debug)]
+pub struct PrivateKey {
+    key_pair: RsaKeyPair,
+}
+
+impl PrivateKey {
+    pub fn from_pem(pem: &[u8]) -> Result<Self, Error> {
+        let (_, pem) =
+            x509_parser::pem::parse_x509_pem(pem).map_err(|_| Error::InvalidPrivateKey)?;
+        let key_pair = match pem.label.as_str() {
+            "RSA PRIVATE KEY" => RsaKeyPair::from_der(&pem.contents),
+            "PRIVATE KEY" => RsaKeyPair::from_pkcs8(&pem.contents),
+            _ => return Err(Error::InvalidPrivateKey),
+        }
+        .map_err(|_| Error::InvalidPrivateKey)?;
+        Ok(Self { key_pair })
+    }
+
+    /// The public key in the PEM encoded SubjectPublicKeyInfo format Hex clients
+    /// use to verify the registry, as served at `/public_key`.
+    pub fn public_key_pem(&self) -> String {
+        // DER encoded AlgorithmIdentifier for rsaEncryption with NULL parameters
+        const RSA_ALGORITHM: &[u8] = &[
+            0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01, 0x05,
+            0x00,
+        ];
+        let mut bit_string = vec![0];
+        bit_string.extend_from_slice(self.key_pair.public().as_ref());
+        let mut spki = RSA_ALGORITHM.to_vec();
+        spki.extend(der_value(0x03, &bit_string));
+        let spki = der_value(0x30, &spki);
+
+        let encoded = base64::engine::general_purpose::STANDARD.encode(spki);
+        let mut pem = String::from("-----BEGIN PUBLIC KEY-----\n");
+        for line in encoded.as_bytes().chunks(64) {
+            pem.push_str(std::str::from_utf8(line).expect("base64 is ascii"));
+            pem.push('\n');
+        }
+        pem.push_str("-----END PUBLIC KEY-----\n");
+        pem
+    }
+
+    /// Sign a payload, returning the encoded `Signed` protobuf message.
+    pub fn sign(&self, payload: Vec<u8>) -> Result<Vec<u8>, Error> {
+        let mut signature = vec![0; self.key_pair.public().modulus_len()];
+        self.key_pair
+            .sign(
+                &RSA_PKCS1_SHA512,
+                &SystemRandom::new(),
+                &payload,
+                &mut signature,
+            )
+            .map_err(|_| Error::Signing)?;
+        let signed = Signed {
+            payload,
+            signature: Some(signature),
+        };
+        Ok(signed.encode_to_vec())
+    }
+}
+
+/// Encode the `/names` resource, listing every package in the repository.
+pub fn encode_names(
+    repository: &str,
+    names: &[String],
+    private_key: &PrivateKey,
+) -> Result<Vec<u8>, Error> {
+    let mut names = names.to_vec();
+    names.sort();
+    let names = Names {
+        packages: names
+            .into_iter()
+            .map(|name| proto::names::Package { name })
+            .collect(),
+        repository: repository.to_string(),
+    };
+    sign_and_compress(names.encode_to_vec(), private_key)
+}
+
+/// Encode the `/versions` resource, listing the versions of every package in
+/// the repository and which of them have been retired.
+pub fn encode_versions(
+    versions: &RegistryVersions,
+    private_key: &PrivateKey,
+) -> Result<Vec<u8>, Error> {
+    let mut packages = versions.packages.iter().collect::<Vec<_>>();
+    packages.sort_by_key(|(name, _)| *name);
+    let packages = packages
+        .into_iter()
+        .map(|(name, versions)| proto::versions::VersionsPackage {
+            name: name.clone(),
+            versions: versions.iter().map(|v| v.version.to_string()).collect(),
+            retired: (0..)
+                .zip(versions)
+                .filter(|(_, v)| v.retired)
+                .map(|(i, _)| i)
+                .collect(),
+        })
+        .collect();
+    let versions = Versions {
+        packages,
+        repository: versions.repository.clone(),
+    };
+    sign_and_compress(versions.encode_to_vec(), private_key)
+}
+
+/// Encode the `/packages/:name` resource, containing the releases of a
+/// package.
+pub fn encode_package(package: &Package, private_key: &PrivateKey) -> Result<Vec<u8>, Error> {
+    let package = proto::package::Package {
+        releases: package.releases.iter().map(release_to_proto).collect(),
+        name: package.name.clone(),
+        repository: package.repository.clone(),
+    };
+    sign_and_compress(package.encode_to_vec(), private_key)
+}
+
+/// Encode a DER tag-length-value.
+fn der_value(tag: u8, value: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag];
+    match value.len() {
+        length @ 0..=0x7f => encoded.push(length as u8),
+        length => {
+            let bytes = length.to_be_bytes();
+            let skip = bytes.iter().take_while(|b| **b == 0).count();
+            encoded.push(0x80 | (bytes.len() - skip) as u8);
+            encoded.extend_from_slice(&bytes[skip..]);
+        }
+    }
+    encoded.extend_from_slice(value);
+    encoded
+}
+
+fn sign_and_compress(payload: Vec<u8>, private_key: &PrivateKey) -> Result<Vec<u8>, Error> {
+    let signed = private_key.sign(payload)?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&signed)?;
+    Ok(encoder.finish()?)
+}
+
+fn release_to_proto<Meta>(release: &Release<Meta>) -> proto::package::Release {
+    let mut requirements = release.requirements.iter().collect::<Vec<_>>();
+    requirements.sort_by_key(|(name, _)| *name);
+    let dependencies = requirements
+        .into_iter()
+        .map(|(name, dependency)| proto::package::Dependency {
+            package: name.clone(),
+            requirement: dependency.requirement.as_str().to_string(),
+            optional: dependency.optional.then_some(true),
+            app: dependency.app.clone(),
+            repository: dependency.repository.clone(),
+        })
+        .collect();
+
+    let retired = release.retirement_status.as_ref().map(|status| {
+        let message = Some(status.message.clone()).filter(|message| !message.is_empty());
+        proto::package::RetirementStatus {
+            reason: retirement_reason_to_proto(&status.reason) as i32,
+            message,
+        }
+    });
+
+    proto::package::Release {
+        version: release.version.to_string(),
+        inner_checksum: release.inner_checksum.clone(),
+        dependencies,
+        retired,
+        outer_checksum: Some(release.outer_checksum.clone()),
+    }
+}
+
+fn retirement_reason_to_proto(reason: &RetirementReason) -> proto::package::RetirementReason {
+    use proto::package::RetirementReason::*;
+    match reason {
+        RetirementReason::Other => RetiredOther,
+        RetirementReason::Invalid => RetiredInvalid,
+        RetirementReason::Security => RetiredSecurity,
+        RetirementReason::Deprecated => RetiredDeprecated,
+        RetirementReason::Renamed => RetiredRenamed,
+    }
+}
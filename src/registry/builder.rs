@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::{Error, PrivateKey, encode_names, encode_package, encode_versions};
+use crate::tarball::ReleaseArchive;
+use crate::{Package, RegistryVersion, RegistryVersions, Release};
+
+/// Builds the resources of a static Hex repository from package tarballs,
+/// equivalent to `mix hex.registry build`.
+///
+/// The resulting directory can be served by any static file server and used
+/// as the [`Config::repository_base`](crate::Config::repository_base).
+///
+/// ```no_run
+/// use std::path::Path;
+/// use hexpm::registry::{PrivateKey, RegistryBuilder};
+///
+/// let private_key = PrivateKey::from_pem(&std::fs::read("private_key.pem").unwrap()).unwrap();
+/// let public = Path::new("public");
+///
+/// RegistryBuilder::new("my_repo")
+///     .add_tarballs_directory(&public.join("tarballs"))
+///     .unwrap()
+///     .build(&private_key)
+///     .unwrap()
+///     .write(public)
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegistryBuilder {
+    repository: String,
+    packages: BTreeMap<String, Vec<Release<()>>>,
+}
+
+impl RegistryBuilder {
+    pub fn new(repository: impl Into<String>) -> Self {
+        Self {
+            repository: repository.into(),
+            packages: BTreeMap::new(),
+        }
+    }
+
+    /// Add a package release from its tarball. The release's dependencies and
+    /// checksums are taken from the tarball.
+    pub fn add_tarball(&mut self, data: &[u8]) -> Result<&mut Self, Error> {
+        let archive = ReleaseArchive::parse(data)?;
+        let _ = self.add_archive(archive)?;
+        Ok(self)
+    }
+
+    /// Add every `<name>-<version>.tar` file in a directory, such as the
+    /// `tarballs` directory of an existing repository.
+    pub fn add_tarballs_directory(&mut self, directory: &Path) -> Result<&mut Self, Error> {
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            if !path.is_file() || path.extension().is_none_or(|extension| extension != "tar") {
+                continue;
+            }
+
+            let archive = ReleaseArchive::parse(&std::fs::read(&path)?)?;
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let expected = format!("{}-{}.tar", archive.metadata.name, archive.metadata.version);
+            if file_name != expected {
+                return Err(Error::UnexpectedTarballName(file_name.into(), expected));
+            }
+            let _ = self.add_archive(archive)?;
+        }
+        Ok(self)
+    }
+
+    fn add_archive(&mut self, archive: ReleaseArchive) -> Result<&mut Self, Error> {
+        let metadata = archive.metadata;
+        let releases = self.packages.entry(metadata.name.clone()).or_default();
+        if releases.iter().any(|r| r.version == metadata.version) {
+            return Err(Error::DuplicateRelease(
+                metadata.name,
+                metadata.version.to_string(),
+            ));
+        }
+        releases.push(Release {
+            version: metadata.version,
+            requirements: metadata.requirements,
+            retirement_status: None,
+            inner_checksum: archive.inner_checksum,
+            outer_checksum: archive.outer_checksum,
+            meta: (),
+        });
+        releases.sort_by(|a, b| a.version.cmp(&b.version));
+        Ok(self)
+    }
+
+    /// Encode and sign every resource of the repository.
+    pub fn build(&self, private_key: &PrivateKey) -> Result<Registry, Error> {
+        let names = self.packages.keys().cloned().collect::<Vec<_>>();
+        let names = encode_names(&self.repository, &names, private_key)?;
+
+        let versions = RegistryVersions {
+            repository: self.repository.clone(),
+            packages: self
+                .packages
+                .iter()
+                .map(|(name, releases)| {
+                    let versions = releases
+                        .iter()
+                        .map(|release| RegistryVersion {
+                            version: release.version.clone(),
+                            retired: release.is_retired(),
+                        })
+                        .collect();
+                    (name.clone(), versions)
+                })
+                .collect(),
+        };
+        let versions = encode_versions(&versions, private_key)?;
+
+        let packages = self
+            .packages
+            .iter()
+            .map(|(name, releases)| {
+                let package = Package {
+                    name: name.clone(),
+                    repository: self.repository.clone(),
+                    releases: releases.clone(),
+                };
+                Ok((name.clone(), encode_package(&package, private_key)?))
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Registry {
+            names,
+            versions,
+            packages,
+            public_key: private_key.public_key_pem(),
+        })
+    }
+}
+
+/// The encoded and signed resources of a static Hex repository.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Registry {
+    /// The gzipped `/names` resource.
+    pub names: Vec<u8>,
+    /// The gzipped `/versions` resource.
+    pub versions: Vec<u8>,
+    /// The gzipped `/packages/<name>` resource of each package.
+    pub packages: BTreeMap<String, Vec<u8>>,
+    /// The PEM encoded `/public_key` used to verify the other resources.
+    pub public_key: String,
+}
+
+impl Registry {
+    /// Write the resources into a directory, replacing any existing files.
+    /// Package tarballs are expected to be in the `tarballs` subdirectory and
+    /// are not written.
+    pub fn write(&self, directory: &Path) -> Result<(), Error> {
+        let packages = directory.join("packages");
+        std::fs::create_dir_all(&packages)?;
+        std::fs::write(directory.join("names"), &self.names)?;
+        std::fs::write(directory.join("versions"), &self.versions)?;
+        std::fs::write(directory.join("public_key"), &self.public_key)?;
+        for (name, package) in &self.packages {
+            std::fs::write(packages.join(name), package)?;
+        }
+        Ok(())
+    }
+}
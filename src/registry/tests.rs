@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use super::*;
+use crate::version::{Range, Version};
+use crate::{ApiError, Dependency, RegistryVersion, RetirementStatus};
+
+const PUBLIC_KEY: &[u8] = std::include_bytes!("../../test/example_public_key");
+
+fn private_key() -> PrivateKey {
+    PrivateKey::from_pem(std::include_bytes!("../../test/example_private_key")).unwrap()
+}
+
+fn response(body: Vec<u8>) -> http::Response<Vec<u8>> {
+    http::Response::builder().status(200).body(body).unwrap()
+}
+
+fn release(version: &str) -> Release<()> {
+    Release {
+        version: Version::parse(version).unwrap(),
+        requirements: HashMap::new(),
+        retirement_status: None,
+        inner_checksum: vec![1; 32],
+        outer_checksum: vec![2; 32],
+        meta: (),
+    }
+}
+
+#[test]
+fn names_round_trip() {
+    let names = vec!["jason".to_string(), "exfmt".to_string()];
+    let encoded = encode_names("hexpm", &names, &private_key()).unwrap();
+    let decoded = crate::repository_v2_get_names_response(response(encoded), PUBLIC_KEY).unwrap();
+    assert_eq!(decoded, vec!["exfmt", "jason"]);
+}
+
+#[test]
+fn versions_round_trip() {
+    let versions = RegistryVersions {
+        repository: "my_repo".into(),
+        packages: [
+            (
+                "exfmt".into(),
+                vec![
+                    RegistryVersion {
+                        version: Version::new(0, 1, 0),
+                        retired: true,
+                    },
+                    RegistryVersion {
+                        version: Version::new(0, 2, 0),
+                        retired: false,
+                    },
+                    RegistryVersion {
+                        version: Version::parse("1.0.0-rc1").unwrap(),
+                        retired: true,
+                    },
+                ],
+            ),
+            ("jason".into(), vec![]),
+        ]
+        .into(),
+    };
+    let encoded = encode_versions(&versions, &private_key()).unwrap();
+
+    let decoded =
+        crate::repository_v2_get_registry_versions_response(response(encoded.clone()), PUBLIC_KEY)
+            .unwrap();
+    assert_eq!(decoded, versions);
+
+    let decoded =
+        crate::repository_v2_get_versions_response(response(encoded), PUBLIC_KEY).unwrap();
+    assert_eq!(
+        decoded.get("exfmt").unwrap(),
+        &vec![
+            Version::new(0, 1, 0),
+            Version::new(0, 2, 0),
+            Version::parse("1.0.0-rc1").unwrap(),
+        ]
+    );
+}
+
+#[test]
+fn package_round_trip() {
+    let mut retired = release("0.1.0");
+    retired.retirement_status = Some(RetirementStatus {
+        reason: RetirementReason::Security,
+        message: "Please upgrade".into(),
+    });
+    let mut with_dependencies = release("0.2.0");
+    with_dependencies.requirements = [
+        (
+            "gleam_stdlib".into(),
+            Dependency {
+                requirement: Range::new("~> 0.14".into()).unwrap(),
+                optional: false,
+                app: None,
+                repository: None,
+            },
+        ),
+        (
+            "private_dep".into(),
+            Dependency {
+                requirement: Range::new(">= 1.0.0".into()).unwrap(),
+                optional: true,
+                app: Some("private_app".into()),
+                repository: Some("my_org".into()),
+            },
+        ),
+    ]
+    .into();
+    let package = Package {
+        name: "my_package".into(),
+        repository: "hexpm".into(),
+        releases: vec![retired, with_dependencies],
+    };
+
+    let encoded = encode_package(&package, &private_key()).unwrap();
+    let decoded = crate::repository_v2_get_package_response(response(encoded), PUBLIC_KEY).unwrap();
+    assert_eq!(decoded, package);
+}
+
+#[test]
+fn pkcs8_private_key() {
+    let private_key =
+        PrivateKey::from_pem(std::include_bytes!("../../test/example_private_key_pkcs8")).unwrap();
+    let encoded = encode_names("hexpm", &["exfmt".into()], &private_key).unwrap();
+    let decoded = crate::repository_v2_get_names_response(response(encoded), PUBLIC_KEY).unwrap();
+    assert_eq!(decoded, vec!["exfmt"]);
+}
+
+#[test]
+fn signature_does_not_match_other_key() {
+    let encoded = encode_names("hexpm", &["exfmt".into()], &private_key()).unwrap();
+    let error = crate::repository_v2_get_names_response(
+        response(encoded),
+        std::include_bytes!("../../test/public_key"),
+    )
+    .unwrap_err();
+    assert!(matches!(error, ApiError::IncorrectPayloadSignature));
+}
+
+#[test]
+fn invalid_private_key() {
+    let public_key = PrivateKey::from_pem(PUBLIC_KEY);
+    assert!(matches!(public_key, Err(Error::InvalidPrivateKey)));
+
+    let not_pem = PrivateKey::from_pem(b"not a key");
+    assert!(matches!(not_pem, Err(Error::InvalidPrivateKey)));
+}
+
+#[test]
+fn public_key_pem() {
+    assert_eq!(
+        private_key().public_key_pem().as_bytes(),
+        PUBLIC_KEY,
+        "derived public key should match the one written by openssl"
+    );
+}
+
+fn tarball(name: &str, version: Version) -> crate::tarball::Tarball {
+    use crate::tarball::{PackageMetadata, TarballBuilder};
+    let mut metadata = PackageMetadata::new(name, version);
+    metadata.requirements = [(
+        "gleam_stdlib".into(),
+        Dependency {
+            requirement: Range::new("~> 0.14".into()).unwrap(),
+            optional: false,
+            app: None,
+            repository: None,
+        },
+    )]
+    .into();
+    TarballBuilder::new(metadata)
+        .add_file("src/main.gleam", "pub fn main() { Nil }")
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn build_static_registry() {
+    let directory = std::env::temp_dir().join(format!("hexpm-registry-{}", std::process::id()));
+    let tarballs = directory.join("tarballs");
+    std::fs::create_dir_all(&tarballs).unwrap();
+    let releases = [
+        ("my_package", Version::new(1, 0, 0)),
+        ("my_package", Version::new(0, 1, 0)),
+        ("other_package", Version::new(2, 0, 0)),
+    ];
+    for (name, version) in &releases {
+        let tarball = tarball(name, version.clone());
+        let path = tarballs.join(format!("{}-{}.tar", name, version));
+        std::fs::write(path, tarball.data).unwrap();
+    }
+    std::fs::write(tarballs.join("README.md"), "not a tarball").unwrap();
+
+    RegistryBuilder::new("my_repo")
+        .add_tarballs_directory(&tarballs)
+        .unwrap()
+        .build(&private_key())
+        .unwrap()
+        .write(&directory)
+        .unwrap();
+
+    let read = |path: &str| std::fs::read(directory.join(path)).unwrap();
+    let public_key = read("public_key");
+
+    let names = crate::repository_v2_get_names_response(response(read("names")), &public_key);
+    assert_eq!(names.unwrap(), vec!["my_package", "other_package"]);
+
+    let versions =
+        crate::repository_v2_get_versions_response(response(read("versions")), &public_key);
+    assert_eq!(
+        versions.unwrap().get("my_package").unwrap(),
+        &vec![Version::new(0, 1, 0), Version::new(1, 0, 0)]
+    );
+
+    let package = crate::repository_v2_get_package_response(
+        response(read("packages/my_package")),
+        &public_key,
+    )
+    .unwrap();
+    assert_eq!(package.repository, "my_repo");
+    assert_eq!(package.releases.len(), 2);
+    let release = &package.releases[1];
+    let expected = tarball("my_package", Version::new(1, 0, 0));
+    assert_eq!(release.version, Version::new(1, 0, 0));
+    assert_eq!(release.outer_checksum, expected.outer_checksum);
+    assert_eq!(release.inner_checksum, expected.inner_checksum);
+    assert!(release.requirements.contains_key("gleam_stdlib"));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn build_rejects_misnamed_tarball() {
+    let directory = std::env::temp_dir().join(format!("hexpm-misnamed-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let tarball = tarball("my_package", Version::new(1, 0, 0));
+    std::fs::write(directory.join("my_package.tar"), tarball.data).unwrap();
+
+    let error = RegistryBuilder::new("my_repo")
+        .add_tarballs_directory(&directory)
+        .unwrap_err();
+    assert!(matches!(
+        error,
+        Error::UnexpectedTarballName(name, expected)
+            if name == "my_package.tar" && expected == "my_package-1.0.0.tar"
+    ));
+
+    std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn build_rejects_duplicate_release() {
+    let tarball = tarball("my_package", Version::new(1, 0, 0));
+    let mut builder = RegistryBuilder::new("my_repo");
+    let _ = builder.add_tarball(&tarball.data).unwrap();
+    let error = builder.add_tarball(&tarball.data).unwrap_err();
+    assert!(matches!(error, Error::DuplicateRelease(..)));
+}
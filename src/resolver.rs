@@ -0,0 +1,536 @@
+//! Dependency resolution for Hex packages, using the PubGrub algorithm.
+//!
+//! Package information is supplied by a [`PackageFetcher`], such as a
+//! [`Client`] or an in-memory collection of packages.
+//!
+//! The resolver follows the rules used by Hex:
+//!
+//! - Optional dependencies are only resolved if another package depends on
+//!   them without the optional flag, in which case the optional requirement
+//!   still applies.
+//! - Retired releases are only selected if they are already locked.
+//! - Pre-releases are only selected if they are locked or the requirements on
+//!   the package start at a pre-release, i.e. `~> 1.0.0-rc.1`.
+//!
+//! Optional dependencies are expressed as constraints on virtual packages, see
+//! [`ResolverPackage`], so the outcome depends only on the releases that end up
+//! selected and not on the order PubGrub visits them in.
+
+mod report;
+#[cfg(test)]
+mod tests;
+
+pub use self::report::NoSolution;
+
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Bound;
+
+use pubgrub::{Dependencies, DependencyConstraints, PackageResolutionStatistics, PubGrubError};
+use thiserror::Error;
+
+use self::report::RequirementSpecs;
+use crate::client::{Client, HttpClient};
+use crate::version::{Range, Version};
+use crate::{ApiError, Dependency, Package, Release};
+
+/// The tree explaining why resolution failed, as produced by PubGrub.
+pub type DerivationTree = pubgrub::DerivationTree<ResolverPackage, pubgrub::Range<Version>, String>;
+
+/// A package as seen by PubGrub.
+///
+/// Alongside the Hex packages themselves there are two kinds of virtual
+/// package, each with the versions 0.0.0 (no) and 1.0.0 (yes), which together
+/// say that an optional dependency either is not used at all or satisfies its
+/// requirement.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ResolverPackage {
+    /// A Hex package, or the root package.
+    Package(String),
+    /// Whether the optional dependency of a release is used, in which case its
+    /// requirement applies.
+    Optional {
+        dependent: String,
+        version: Version,
+        dependency: String,
+    },
+    /// Whether a package is part of the solution.
+    Used(String),
+}
+
+impl ResolverPackage {
+    /// The name of the Hex package this is about.
+    pub fn name(&self) -> &str {
+        match self {
+            ResolverPackage::Package(name) | ResolverPackage::Used(name) => name,
+            ResolverPackage::Optional { dependency, .. } => dependency,
+        }
+    }
+}
+
+impl fmt::Display for ResolverPackage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolverPackage::Package(name) => write!(f, "{name}"),
+            ResolverPackage::Optional {
+                dependent,
+                version,
+                dependency,
+            } => write!(f, "{dependent} {version} using {dependency}"),
+            ResolverPackage::Used(name) => write!(f, "{name} being used"),
+        }
+    }
+}
+
+/// The version of a virtual package meaning yes.
+fn yes() -> Version {
+    Version::new(1, 0, 0)
+}
+
+/// The version of a virtual package meaning no.
+fn no() -> Version {
+    Version::new(0, 0, 0)
+}
+
+/// A source of package information for the resolver.
+pub trait PackageFetcher {
+    /// Get the releases of a package. Returning [`ApiError::NotFound`] marks
+    /// the package as having no versions rather than failing resolution.
+    ///
+    /// The repository is the one named by the dependency on the package, as
+    /// in [`Dependency::repository`], or `None` for the default repository of
+    /// the fetcher.
+    fn get_package(&self, name: &str, repository: Option<&str>) -> Result<Package, ApiError>;
+}
+
+/// Packages without a repository are fetched from the repository of the
+/// client's config, and others from the named hex.pm repository, verified
+/// with the organization's key from
+/// [`Client::organization_public_keys`] when there is one.
+impl<T: HttpClient> PackageFetcher for Client<T> {
+    fn get_package(&self, name: &str, repository: Option<&str>) -> Result<Package, ApiError> {
+        match repository {
+            Some(repository) => Client::get_repository_package(self, name, repository),
+            None => Client::get_package(self, name),
+        }
+    }
+}
+
+/// Packages are looked up by name alone, whatever the repository.
+impl PackageFetcher for HashMap<String, Package> {
+    fn get_package(&self, name: &str, _repository: Option<&str>) -> Result<Package, ApiError> {
+        self.get(name).cloned().ok_or(ApiError::NotFound(None))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ResolutionError {
+    #[error("unable to find compatible versions for the version constraints")]
+    NoSolution(Box<NoSolution>),
+
+    #[error("failed to get the package {0}: {1}")]
+    Fetch(String, #[source] ApiError),
+
+    #[error(
+        "{package} is specified with the requirement `{requirement}`, \
+but it is locked to {locked}, which is incompatible"
+    )]
+    LockedVersionConflict {
+        package: String,
+        requirement: String,
+        locked: Version,
+    },
+}
+
+impl ResolutionError {
+    /// A description of why resolution failed. See
+    /// [`NoSolution::explanation`].
+    pub fn explanation(&self) -> String {
+        match self {
+            ResolutionError::NoSolution(no_solution) => no_solution.explanation(),
+            error => error.to_string(),
+        }
+    }
+}
+
+/// Resolve the versions of all packages needed to satisfy the requirements of
+/// the root package, returning the selected version of each package other
+/// than the root.
+///
+/// Locked packages are always kept at their locked version.
+///
+/// ```
+/// use std::collections::HashMap;
+/// use hexpm::{Package, resolver, version::Range};
+///
+/// let packages: HashMap<String, Package> = HashMap::new();
+/// let requirements = vec![("gleam_stdlib".to_string(), Range::new("~> 1.0".into()).unwrap())];
+///
+/// let error = resolver::resolve_versions(&packages, "my_app", requirements, &HashMap::new());
+/// assert!(error.is_err());
+/// ```
+pub fn resolve_versions<Requirements>(
+    fetcher: &impl PackageFetcher,
+    root_name: &str,
+    requirements: Requirements,
+    locked: &HashMap<String, Version>,
+) -> Result<HashMap<String, Version>, ResolutionError>
+where
+    Requirements: IntoIterator<Item = (String, Range)>,
+{
+    let root_version = Version::new(0, 0, 0);
+    let root = Package {
+        name: root_name.to_string(),
+        repository: "local".into(),
+        releases: vec![Release {
+            version: root_version.clone(),
+            requirements: root_dependencies(requirements, locked)?,
+            retirement_status: None,
+            inner_checksum: vec![],
+            outer_checksum: vec![],
+            meta: (),
+        }],
+    };
+
+    let provider = DependencyProvider::new(fetcher, root, locked);
+    let root_package = ResolverPackage::Package(root_name.to_string());
+    let versions = pubgrub::resolve(&provider, root_package, root_version)
+        .map_err(|error| match error {
+            PubGrubError::NoSolution(tree) => {
+                let no_solution = NoSolution::new(
+                    tree,
+                    root_name.to_string(),
+                    provider.requirements.take(),
+                    provider.missing.take(),
+                );
+                ResolutionError::NoSolution(Box::new(no_solution))
+            }
+            PubGrubError::ErrorRetrievingDependencies {
+                package, source, ..
+            }
+            | PubGrubError::ErrorChoosingVersion { package, source } => {
+                ResolutionError::Fetch(package.name().to_string(), source)
+            }
+            PubGrubError::ErrorInShouldCancel(source) => {
+                ResolutionError::Fetch(root_name.to_string(), source)
+            }
+        })?
+        .into_iter()
+        .filter_map(|(package, version)| match package {
+            ResolverPackage::Package(name) if name != root_name => Some((name, version)),
+            _ => None,
+        })
+        .collect();
+    Ok(versions)
+}
+
+fn root_dependencies<Requirements>(
+    requirements: Requirements,
+    locked: &HashMap<String, Version>,
+) -> Result<HashMap<String, Dependency>, ResolutionError>
+where
+    Requirements: IntoIterator<Item = (String, Range)>,
+{
+    let dependency = |requirement| Dependency {
+        requirement,
+        optional: false,
+        app: None,
+        repository: None,
+    };
+
+    // Record all of the already locked versions as hard requirements
+    let mut dependencies: HashMap<_, _> = locked
+        .iter()
+        .map(|(name, version)| (name.clone(), dependency(version.clone().into())))
+        .collect();
+
+    for (name, range) in requirements {
+        match locked.get(&name) {
+            // If the package was not already locked then we can use the
+            // specified version requirement without modification.
+            None => {
+                let _ = dependencies.insert(name, dependency(range));
+            }
+
+            // If the version was locked we verify that the requirement is
+            // compatible with the locked version.
+            Some(locked) if range.to_pubgrub().contains(locked) => (),
+            Some(locked) => {
+                return Err(ResolutionError::LockedVersionConflict {
+                    package: name,
+                    requirement: range.as_str().to_string(),
+                    locked: locked.clone(),
+                });
+            }
+        }
+    }
+
+    Ok(dependencies)
+}
+
+struct DependencyProvider<'a, F> {
+    fetcher: &'a F,
+    locked: &'a HashMap<String, Version>,
+    /// Fetched packages, with releases that cannot be selected removed and the
+    /// remaining releases sorted highest first.
+    packages: RefCell<HashMap<String, Package>>,
+    /// The repository each package is fetched from, which is the one named by
+    /// the first dependency on it, or else the repository of the package that
+    /// depends on it. `None` is the default repository of the fetcher.
+    repositories: RefCell<HashMap<String, Option<String>>>,
+    /// The requirements as written by each package, used to explain failures.
+    requirements: RefCell<RequirementSpecs>,
+    /// Packages that do not exist.
+    missing: RefCell<HashSet<String>>,
+}
+
+impl<'a, F: PackageFetcher> DependencyProvider<'a, F> {
+    fn new(fetcher: &'a F, root: Package, locked: &'a HashMap<String, Version>) -> Self {
+        let provider = Self {
+            fetcher,
+            locked,
+            packages: RefCell::new(HashMap::new()),
+            repositories: RefCell::new(HashMap::new()),
+            requirements: RefCell::new(HashMap::new()),
+            missing: RefCell::new(HashSet::new()),
+        };
+        let _ = provider
+            .packages
+            .borrow_mut()
+            .insert(root.name.clone(), root);
+        provider
+    }
+
+    /// Download information about the package if it has not already been
+    /// downloaded. Returns `false` if the package does not exist.
+    fn ensure_package_fetched(&self, name: &str) -> Result<bool, ApiError> {
+        if self.packages.borrow().contains_key(name) {
+            return Ok(true);
+        }
+
+        let repository = self.repositories.borrow().get(name).cloned().flatten();
+        let mut package = match self.fetcher.get_package(name, repository.as_deref()) {
+            Ok(package) => package,
+            Err(ApiError::NotFound(_)) => {
+                let _ = self.missing.borrow_mut().insert(name.to_string());
+                return Ok(false);
+            }
+            Err(error) => return Err(error),
+        };
+
+        // Retired releases are only used if they have already been locked.
+        let locked = self.locked.get(name);
+        package
+            .releases
+            .retain(|release| !release.is_retired() || Some(&release.version) == locked);
+        package.releases.sort_by(|a, b| b.version.cmp(&a.version));
+
+        let _ = self.packages.borrow_mut().insert(name.to_string(), package);
+        Ok(true)
+    }
+
+    /// The requirements of a fetched release.
+    fn release_requirements(
+        &self,
+        name: &str,
+        version: &Version,
+    ) -> Option<HashMap<String, Dependency>> {
+        self.packages
+            .borrow()
+            .get(name)?
+            .releases
+            .iter()
+            .find(|release| &release.version == version)
+            .map(|release| release.requirements.clone())
+    }
+
+    fn choose_package_version(
+        &self,
+        name: &str,
+        range: &pubgrub::Range<Version>,
+    ) -> Result<Option<Version>, ApiError> {
+        if !self.ensure_package_fetched(name)? {
+            return Ok(None);
+        }
+
+        let locked = self.locked.get(name);
+        let allow_pre = starts_at_pre_release(range);
+        let packages = self.packages.borrow();
+        let version = packages[name]
+            .releases
+            .iter()
+            .map(|release| &release.version)
+            .filter(|version| !version.is_pre() || allow_pre || Some(*version) == locked)
+            .find(|version| range.contains(version))
+            .cloned();
+        Ok(version)
+    }
+
+    fn package_dependencies(
+        &self,
+        name: &str,
+        version: &Version,
+    ) -> Result<Dependencies<ResolverPackage, pubgrub::Range<Version>, String>, ApiError> {
+        if !self.ensure_package_fetched(name)? {
+            return Ok(Dependencies::Unavailable(
+                "the package does not exist".into(),
+            ));
+        }
+        let Some(requirements) = self.release_requirements(name, version) else {
+            return Ok(Dependencies::Unavailable(
+                "the release does not exist".into(),
+            ));
+        };
+
+        // Sorted so that resolution, and any failure explanation, is the same
+        // every time.
+        let mut requirements = requirements.into_iter().collect::<Vec<_>>();
+        requirements.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        // Selecting a package rules out skipping any optional dependency on it.
+        let mut dependencies = DependencyConstraints::default();
+        let _ = dependencies.insert(
+            ResolverPackage::Used(name.to_string()),
+            pubgrub::Range::singleton(yes()),
+        );
+
+        let repository = self.repositories.borrow().get(name).cloned().flatten();
+        for (dependency_name, dependency) in requirements {
+            let _ = self
+                .repositories
+                .borrow_mut()
+                .entry(dependency_name.clone())
+                .or_insert_with(|| dependency.repository.clone().or(repository.clone()));
+
+            let range = dependency.requirement.to_pubgrub().clone();
+            self.requirements
+                .borrow_mut()
+                .entry((name.to_string(), dependency_name.clone()))
+                .or_default()
+                .push((range.clone(), dependency.requirement.as_str().to_string()));
+
+            // The requirement of an optional dependency is applied by the
+            // virtual package deciding whether it is used.
+            if dependency.optional {
+                let optional = ResolverPackage::Optional {
+                    dependent: name.to_string(),
+                    version: version.clone(),
+                    dependency: dependency_name,
+                };
+                let _ = dependencies.insert(optional, pubgrub::Range::full());
+            } else {
+                let _ = dependencies.insert(ResolverPackage::Package(dependency_name), range);
+            }
+        }
+
+        Ok(Dependencies::Available(dependencies))
+    }
+
+    fn optional_dependencies(
+        &self,
+        dependent: &str,
+        dependent_version: &Version,
+        dependency: &str,
+        version: &Version,
+    ) -> Dependencies<ResolverPackage, pubgrub::Range<Version>, String> {
+        // When the optional dependency is not used no other package may use it
+        // either.
+        if version == &no() {
+            let used = ResolverPackage::Used(dependency.to_string());
+            return Dependencies::Available(
+                [(used, pubgrub::Range::singleton(no()))]
+                    .into_iter()
+                    .collect(),
+            );
+        }
+
+        let range = self
+            .release_requirements(dependent, dependent_version)
+            .and_then(|requirements| requirements.get(dependency).cloned())
+            .map(|dependency| dependency.requirement.to_pubgrub().clone());
+        match range {
+            Some(range) => {
+                let package = ResolverPackage::Package(dependency.to_string());
+                Dependencies::Available([(package, range)].into_iter().collect())
+            }
+            None => Dependencies::Unavailable("the release does not exist".into()),
+        }
+    }
+}
+
+/// Whether the range starts at a pre-release, which is the case when it was
+/// required as `~> 1.0.0-rc.1`.
+fn starts_at_pre_release(range: &pubgrub::Range<Version>) -> bool {
+    matches!(
+        range.iter().next(),
+        Some((Bound::Included(version) | Bound::Excluded(version), _)) if version.is_pre()
+    )
+}
+
+impl<F: PackageFetcher> pubgrub::DependencyProvider for DependencyProvider<'_, F> {
+    type P = ResolverPackage;
+    type V = Version;
+    type VS = pubgrub::Range<Version>;
+    type Priority = (bool, Reverse<usize>);
+    type M = String;
+    type Err = ApiError;
+
+    fn prioritize(
+        &self,
+        package: &ResolverPackage,
+        range: &pubgrub::Range<Version>,
+        _statistics: &PackageResolutionStatistics,
+    ) -> Self::Priority {
+        // Decide the packages with the fewest possible versions first, leaving
+        // whether optional dependencies are used until the packages that may
+        // need them have been decided.
+        let count = match package {
+            ResolverPackage::Package(name) => match self.packages.borrow().get(name) {
+                Some(package) => package
+                    .releases
+                    .iter()
+                    .filter(|release| range.contains(&release.version))
+                    .count(),
+                None => usize::MAX,
+            },
+            ResolverPackage::Optional { .. } | ResolverPackage::Used(_) => [no(), yes()]
+                .iter()
+                .filter(|version| range.contains(version))
+                .count(),
+        };
+        let is_optional = matches!(package, ResolverPackage::Optional { .. });
+        (!is_optional, Reverse(count))
+    }
+
+    fn choose_version(
+        &self,
+        package: &ResolverPackage,
+        range: &pubgrub::Range<Version>,
+    ) -> Result<Option<Version>, ApiError> {
+        match package {
+            ResolverPackage::Package(name) => self.choose_package_version(name, range),
+            // Optional dependencies are not used unless they have to be.
+            ResolverPackage::Optional { .. } | ResolverPackage::Used(_) => Ok([no(), yes()]
+                .into_iter()
+                .find(|version| range.contains(version))),
+        }
+    }
+
+    fn get_dependencies(
+        &self,
+        package: &ResolverPackage,
+        version: &Version,
+    ) -> Result<Dependencies<ResolverPackage, pubgrub::Range<Version>, String>, ApiError> {
+        match package {
+            ResolverPackage::Package(name) => self.package_dependencies(name, version),
+            ResolverPackage::Optional {
+                dependent,
+                version: dependent_version,
+                dependency,
+            } => Ok(self.optional_dependencies(dependent, dependent_version, dependency, version)),
+            ResolverPackage::Used(_) => Ok(Dependencies::Available(Default::default())),
+        }
+    }
+}
@@ -0,0 +1,737 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Bound;
+use std::sync::Arc;
+
+use pubgrub::{DefaultStringReporter, Derived, External, Map, ReportFormatter, Reporter, Term};
+
+use super::{DerivationTree, ResolverPackage, no, yes};
+use crate::version::Version;
+
+type Range = pubgrub::Range<Version>;
+type Incompatibility = External<ResolverPackage, Range, String>;
+type Terms = Map<ResolverPackage, Term<Range>>;
+
+/// The requirement strings of each package on its dependencies, along with
+/// the range each was resolved with.
+pub(super) type RequirementSpecs = HashMap<(String, String), Vec<(Range, String)>>;
+
+/// The cause of a failed resolution.
+#[derive(Debug, Clone)]
+pub struct NoSolution {
+    /// The tree explaining why resolution failed, as produced by PubGrub.
+    pub tree: DerivationTree,
+    root: String,
+    requirements: RequirementSpecs,
+    missing: HashSet<String>,
+}
+
+impl NoSolution {
+    pub(super) fn new(
+        tree: DerivationTree,
+        root: String,
+        requirements: RequirementSpecs,
+        missing: HashSet<String>,
+    ) -> Self {
+        Self {
+            tree,
+            root,
+            requirements,
+            missing,
+        }
+    }
+
+    /// A description of why resolution failed, written in terms of the
+    /// requirements as they were specified by each package, i.e.
+    ///
+    /// ```text
+    /// Because json 1.0.0 requires decoder ~> 1.0 but http 1.0.0 requires decoder ~> 2.0, json 1.0.0 and http 1.0.0 cannot be used together.
+    /// ```
+    ///
+    /// Causes shared by several parts of the explanation are numbered and
+    /// referred to by their number.
+    pub fn explanation(&self) -> String {
+        DefaultStringReporter::report_with_formatter(&simplify(&self.tree), &Formatter(self))
+    }
+}
+
+/// Merge the versions of a package that do not exist into the requirements of
+/// the versions that do, so "no version of foo matches > 1.0.0 and < 2.0.0"
+/// and "foo 1.0.0 requires bar ~> 1.0" becomes "foo ~> 1.0 requires bar ~> 1.0".
+///
+/// Unlike [`pubgrub::DerivationTree::collapse_no_versions`] the range of the
+/// dependency is never changed, so its original requirement can still be
+/// shown.
+///
+/// Derivations involving an optional dependency are rewritten in terms of its
+/// requirement, see [`optional_conflict`].
+fn simplify(tree: &DerivationTree) -> DerivationTree {
+    let DerivationTree::Derived(derived) = tree else {
+        return tree.clone();
+    };
+    if let Some(tree) = optional_conflict(derived) {
+        return tree;
+    }
+    let cause1 = simplify(&derived.cause1);
+    let cause2 = simplify(&derived.cause2);
+    match (&cause1, &cause2) {
+        (
+            DerivationTree::External(External::NoVersions(package, versions)),
+            DerivationTree::External(External::FromDependencyOf(
+                dependent,
+                range,
+                dependency,
+                dependency_range,
+            )),
+        )
+        | (
+            DerivationTree::External(External::FromDependencyOf(
+                dependent,
+                range,
+                dependency,
+                dependency_range,
+            )),
+            DerivationTree::External(External::NoVersions(package, versions)),
+        ) if package == dependent => DerivationTree::External(External::FromDependencyOf(
+            dependent.clone(),
+            range.union(versions),
+            dependency.clone(),
+            dependency_range.clone(),
+        )),
+        _ => DerivationTree::Derived(Derived {
+            terms: derived.terms.clone(),
+            shared_id: derived.shared_id,
+            cause1: Arc::new(cause1),
+            cause2: Arc::new(cause2),
+        }),
+    }
+}
+
+/// The optional requirement of some versions of a package on another.
+struct OptionalRequirement {
+    dependent: String,
+    versions: Range,
+    /// The virtual package for the optional dependency of one of the versions.
+    optional: ResolverPackage,
+    dependency: String,
+    requirement: Range,
+}
+
+/// Rewrite a derivation that relies on the virtual packages of an optional
+/// dependency in terms of the optional requirement, so it reads "json 1.0.0
+/// optionally requires decoder ~> 1.0 but your project requires decoder ~> 2.0"
+/// rather than describing whether decoder is used.
+///
+/// This is done for derivations between package versions that follow from one
+/// optional requirement, shared by some versions of the dependent, along with:
+///
+/// - nothing else, when a version of the dependency outside the requirement
+///   is one of the terms;
+/// - a requirement of another package on the dependency that cannot be met
+///   along with it;
+/// - or that same contrasting requirement and a requirement of its package on
+///   the dependent.
+///
+/// Versions that do not exist are not mentioned. Other derivations are kept
+/// as they are.
+fn optional_conflict(derived: &Derived<ResolverPackage, Range, String>) -> Option<DerivationTree> {
+    if derived
+        .terms
+        .keys()
+        .any(|package| !matches!(package, ResolverPackage::Package(_)))
+    {
+        return None;
+    }
+
+    let mut externals = vec![];
+    collect_externals(&derived.cause1, &mut externals);
+    collect_externals(&derived.cause2, &mut externals);
+
+    let mut optional: Option<OptionalRequirement> = None;
+    let mut requirements: Vec<&Incompatibility> = vec![];
+    for external in &externals {
+        match external {
+            External::FromDependencyOf(
+                package @ ResolverPackage::Optional {
+                    dependent,
+                    version,
+                    dependency,
+                },
+                _,
+                ResolverPackage::Package(_),
+                requirement,
+            ) => match &mut optional {
+                None => {
+                    optional = Some(OptionalRequirement {
+                        dependent: dependent.clone(),
+                        versions: Range::singleton(version.clone()),
+                        optional: package.clone(),
+                        dependency: dependency.clone(),
+                        requirement: requirement.clone(),
+                    })
+                }
+                Some(optional)
+                    if &optional.dependent == dependent
+                        && &optional.dependency == dependency
+                        && &optional.requirement == requirement =>
+                {
+                    optional.versions = optional.versions.union(&Range::singleton(version.clone()))
+                }
+                Some(_) => return None,
+            },
+            External::FromDependencyOf(
+                ResolverPackage::Package(_),
+                _,
+                ResolverPackage::Package(_),
+                _,
+            ) => {
+                // Shared derivations are visited once for each use.
+                if !requirements
+                    .iter()
+                    .any(|seen| is_same_requirement(seen, external))
+                {
+                    requirements.push(*external)
+                }
+            }
+            External::FromDependencyOf(..) | External::NoVersions(..) => (),
+            External::NotRoot(..) | External::Custom(..) => return None,
+        }
+    }
+    let optional = optional?;
+
+    // Every virtual package must be for this optional dependency.
+    let is_related = |package: &ResolverPackage| match package {
+        ResolverPackage::Package(_) => true,
+        ResolverPackage::Optional {
+            dependent,
+            dependency,
+            ..
+        } => dependent == &optional.dependent && dependency == &optional.dependency,
+        ResolverPackage::Used(name) => name == &optional.dependency,
+    };
+    let all_related = externals.iter().all(|external| match external {
+        External::FromDependencyOf(package, _, dependency, _) => {
+            is_related(package) && is_related(dependency)
+        }
+        External::NoVersions(package, _) => is_related(package),
+        External::NotRoot(..) | External::Custom(..) => false,
+    });
+    if !all_related {
+        return None;
+    }
+
+    let term = |name: &str| match derived.terms.get(&ResolverPackage::Package(name.into())) {
+        Some(Term::Positive(range)) => Some(range),
+        _ => None,
+    };
+    let contrasts = |external: &Incompatibility| match external {
+        External::FromDependencyOf(
+            ResolverPackage::Package(_),
+            _,
+            ResolverPackage::Package(dependency),
+            range,
+        ) => {
+            dependency == &optional.dependency
+                && range.intersection(&optional.requirement).is_empty()
+        }
+        _ => false,
+    };
+    let external = External::FromDependencyOf(
+        ResolverPackage::Package(optional.dependent.clone()),
+        optional.versions.clone(),
+        optional.optional.clone(),
+        optional.requirement.clone(),
+    );
+    let derivation = |terms, cause1, cause2| {
+        DerivationTree::Derived(Derived {
+            terms,
+            shared_id: None,
+            cause1: Arc::new(cause1),
+            cause2: Arc::new(cause2),
+        })
+    };
+
+    match requirements.as_slice() {
+        // i.e. json 1.0.0 and decoder 2.0.0 cannot be used together.
+        [] if derived.terms.len() == 2
+            && term(&optional.dependent).is_some()
+            && term(&optional.dependency)
+                .is_some_and(|range| range.intersection(&optional.requirement).is_empty()) =>
+        {
+            Some(DerivationTree::External(external))
+        }
+
+        // i.e. json 1.0.0 and http 1.0.0 cannot be used together.
+        [contrast @ External::FromDependencyOf(package, ..)]
+            if derived.terms.len() == 2
+                && term(&optional.dependent).is_some()
+                && term(package.name()).is_some()
+                && contrasts(contrast) =>
+        {
+            Some(derivation(
+                derived.terms.clone(),
+                DerivationTree::External(external),
+                DerivationTree::External((*contrast).clone()),
+            ))
+        }
+
+        // i.e. your project requires json and decoder, so cannot be used.
+        [first, second] => {
+            let (contrast, requirement) = if contrasts(first) {
+                (*first, *second)
+            } else {
+                (*second, *first)
+            };
+            let (
+                External::FromDependencyOf(package, range, ..),
+                External::FromDependencyOf(requirer, _, ResolverPackage::Package(required), _),
+            ) = (contrast, requirement)
+            else {
+                return None;
+            };
+            if !contrasts(contrast)
+                || requirer != package
+                || *required != optional.dependent
+                || derived.terms.len() != 1
+                || term(package.name()).is_none()
+            {
+                return None;
+            }
+            let mut terms = Map::default();
+            let _ = terms.insert(
+                ResolverPackage::Package(optional.dependent.clone()),
+                Term::Positive(optional.versions.clone()),
+            );
+            let _ = terms.insert(package.clone(), Term::Positive(range.clone()));
+            let contrast = derivation(
+                terms,
+                DerivationTree::External(external),
+                DerivationTree::External(contrast.clone()),
+            );
+            Some(derivation(
+                derived.terms.clone(),
+                contrast,
+                DerivationTree::External(requirement.clone()),
+            ))
+        }
+
+        _ => None,
+    }
+}
+
+fn is_same_requirement(left: &Incompatibility, right: &Incompatibility) -> bool {
+    match (left, right) {
+        (
+            External::FromDependencyOf(left, left_range, left_dependency, left_dependency_range),
+            External::FromDependencyOf(
+                right,
+                right_range,
+                right_dependency,
+                right_dependency_range,
+            ),
+        ) => {
+            left == right
+                && left_range == right_range
+                && left_dependency == right_dependency
+                && left_dependency_range == right_dependency_range
+        }
+        _ => false,
+    }
+}
+
+fn collect_externals<'a>(tree: &'a DerivationTree, externals: &mut Vec<&'a Incompatibility>) {
+    match tree {
+        DerivationTree::External(external) => externals.push(external),
+        DerivationTree::Derived(derived) => {
+            collect_externals(&derived.cause1, externals);
+            collect_externals(&derived.cause2, externals);
+        }
+    }
+}
+
+struct Formatter<'a>(&'a NoSolution);
+
+/// How a term is written, i.e. "json 1.0.0" or "using decoder" as a noun,
+/// "json 1.0.0 is selected" or "decoder is used" as a statement, and
+/// "json 1.0.0 is required" or "decoder must be used" when required.
+#[derive(Clone, Copy)]
+enum Phrase {
+    Noun,
+    Statement,
+    Required,
+}
+
+impl Formatter<'_> {
+    /// The package and versions, with the root package as "your project".
+    /// Versions that some package required are written as that requirement.
+    fn package(&self, package: &ResolverPackage, range: &Range) -> String {
+        let ResolverPackage::Package(name) = package else {
+            return self.term(package, range, Phrase::Noun);
+        };
+        if name == &self.0.root {
+            "your project".into()
+        } else if range == &Range::full() {
+            name.clone()
+        } else if let Some(version) = range.as_singleton() {
+            format!("{name} {version}")
+        } else if let Some(spec) = self.required_as(name, range) {
+            format!("{name} {spec}")
+        } else {
+            format!("{name} ({})", hex_range(range))
+        }
+    }
+
+    /// How the range of the package was written by any package requiring it.
+    /// When it was written in several ways the first in order is used, so the
+    /// explanation is the same every time.
+    fn required_as(&self, package: &str, range: &Range) -> Option<&str> {
+        self.0
+            .requirements
+            .iter()
+            .filter(|((_, dependency), _)| dependency == package)
+            .flat_map(|(_, specs)| specs)
+            .filter(|(r, _)| r == range)
+            .map(|(_, spec)| spec.as_str())
+            .min()
+    }
+
+    /// The package being within the range. Virtual packages are described by
+    /// what their versions mean.
+    fn term(&self, package: &ResolverPackage, range: &Range, phrase: Phrase) -> String {
+        let answer = match (range.contains(&yes()), range.contains(&no())) {
+            (true, false) => Some(true),
+            (false, true) => Some(false),
+            _ => None,
+        };
+        match package {
+            ResolverPackage::Package(_) => {
+                let package = self.package(package, range);
+                match phrase {
+                    Phrase::Noun => package,
+                    Phrase::Statement => format!("{package} is selected"),
+                    Phrase::Required => format!("{package} is required"),
+                }
+            }
+            ResolverPackage::Used(name) => match (phrase, answer) {
+                (Phrase::Noun, Some(true)) => format!("using {name}"),
+                (Phrase::Noun, Some(false)) => format!("not using {name}"),
+                (Phrase::Statement, Some(true)) => format!("{name} is used"),
+                (Phrase::Statement, Some(false)) => format!("{name} is not used"),
+                (Phrase::Required, Some(true)) => format!("{name} must be used"),
+                (Phrase::Required, Some(false)) => format!("{name} cannot be used"),
+                (_, None) => format!("{name} is either used or not"),
+            },
+            ResolverPackage::Optional {
+                dependent,
+                version,
+                dependency,
+            } => match (phrase, answer) {
+                (Phrase::Noun, Some(true)) => format!("{dependent} {version} using {dependency}"),
+                (Phrase::Noun, Some(false)) => {
+                    format!("{dependent} {version} without {dependency}")
+                }
+                (Phrase::Statement, Some(true)) => {
+                    format!("{dependent} {version} uses {dependency}")
+                }
+                (Phrase::Statement, Some(false)) => {
+                    format!("{dependent} {version} does not use {dependency}")
+                }
+                (Phrase::Required, Some(true)) => {
+                    format!("{dependent} {version} must use {dependency}")
+                }
+                (Phrase::Required, Some(false)) => {
+                    format!("{dependent} {version} cannot use {dependency}")
+                }
+                (_, None) => format!("{dependent} {version} either uses {dependency} or not"),
+            },
+        }
+    }
+
+    /// The requirement as it was written by the dependent package, or by any
+    /// other package for requirements derived from theirs, if known.
+    fn requirement(&self, dependent: &str, dependency: &str, range: &Range) -> String {
+        self.0
+            .requirements
+            .get(&(dependent.to_string(), dependency.to_string()))
+            .and_then(|specs| specs.iter().find(|(r, _)| r == range))
+            .map(|(_, spec)| spec.as_str())
+            .or_else(|| self.required_as(dependency, range))
+            .map_or_else(|| hex_range(range), String::from)
+    }
+
+    fn is_root(&self, external: &Incompatibility) -> bool {
+        matches!(
+            external,
+            External::FromDependencyOf(ResolverPackage::Package(package), ..)
+                if package == &self.0.root
+        )
+    }
+
+    /// Join two causes, contrasting them when they are requirements on the
+    /// same package and mentioning the root package last.
+    fn join(&self, first: &Incompatibility, second: &Incompatibility) -> String {
+        let (first, second) = if self.is_root(first) && !self.is_root(second) {
+            (second, first)
+        } else {
+            (first, second)
+        };
+        // An optional requirement on a package contrasts with any other.
+        let requires = |external: &Incompatibility| match external {
+            External::FromDependencyOf(
+                _,
+                _,
+                dependency @ (ResolverPackage::Package(_) | ResolverPackage::Optional { .. }),
+                _,
+            ) => Some(dependency.name().to_string()),
+            _ => None,
+        };
+        let conjunction = match (requires(first), requires(second)) {
+            (Some(first), Some(second)) if first == second => "but",
+            _ => "and",
+        };
+        format!(
+            "{} {conjunction} {}",
+            self.format_external(first),
+            self.format_external(second)
+        )
+    }
+}
+
+impl ReportFormatter<ResolverPackage, Range, String> for Formatter<'_> {
+    type Output = String;
+
+    fn format_external(&self, external: &Incompatibility) -> String {
+        match external {
+            External::NotRoot(package, version) => format!(
+                "we are resolving {}",
+                self.package(package, &Range::singleton(version.clone()))
+            ),
+            External::NoVersions(ResolverPackage::Package(package), _)
+                if self.0.missing.contains(package) =>
+            {
+                format!("no package named {package} exists")
+            }
+            External::NoVersions(package @ ResolverPackage::Package(_), range)
+                if range == &Range::full() =>
+            {
+                format!("there are no versions of {package}")
+            }
+            External::NoVersions(package @ ResolverPackage::Package(_), range) => {
+                format!("no version of {package} matches {}", hex_range(range))
+            }
+            External::NoVersions(package, _) => {
+                self.term(package, &Range::full(), Phrase::Statement)
+            }
+            External::FromDependencyOf(
+                package @ ResolverPackage::Package(name),
+                range,
+                ResolverPackage::Package(dependency),
+                dependency_range,
+            ) => format!(
+                "{} requires {dependency} {}",
+                self.package(package, range),
+                self.requirement(name, dependency, dependency_range)
+            ),
+            External::FromDependencyOf(
+                package @ ResolverPackage::Package(_),
+                range,
+                ResolverPackage::Optional { dependency, .. },
+                dependency_range,
+            ) if dependency_range == &Range::full() => format!(
+                "{} optionally requires {dependency}",
+                self.package(package, range)
+            ),
+            External::FromDependencyOf(
+                package @ ResolverPackage::Package(name),
+                range,
+                ResolverPackage::Optional { dependency, .. },
+                dependency_range,
+            ) => format!(
+                "{} optionally requires {dependency} {}",
+                self.package(package, range),
+                self.requirement(name, dependency, dependency_range)
+            ),
+            External::FromDependencyOf(
+                package @ ResolverPackage::Optional { dependent, .. },
+                range,
+                ResolverPackage::Package(dependency),
+                dependency_range,
+            ) => format!(
+                "if {} then it requires {dependency} {}",
+                self.term(package, range, Phrase::Statement),
+                self.requirement(dependent, dependency, dependency_range)
+            ),
+            External::FromDependencyOf(package, range, dependency, dependency_range) => format!(
+                "if {} then {}",
+                self.term(package, range, Phrase::Statement),
+                self.term(dependency, dependency_range, Phrase::Statement)
+            ),
+            External::Custom(package, range, reason) => {
+                format!(
+                    "{} cannot be used as {reason}",
+                    self.package(package, range)
+                )
+            }
+        }
+    }
+
+    fn format_terms(&self, terms: &Terms) -> String {
+        let terms = terms.iter().collect::<Vec<_>>();
+        match terms.as_slice() {
+            [] => "version solving failed".into(),
+            [(ResolverPackage::Package(package), Term::Positive(_))] if *package == self.0.root => {
+                "your project's requirements cannot be satisfied".into()
+            }
+            [(package @ ResolverPackage::Package(_), Term::Positive(range))]
+                if range == &Range::full() =>
+            {
+                format!("no version of {package} can be used")
+            }
+            [(package @ ResolverPackage::Package(_), Term::Positive(range))] => {
+                format!("{} cannot be used", self.package(package, range))
+            }
+            [(package, Term::Positive(range))] => {
+                self.term(package, &range.complement(), Phrase::Required)
+            }
+            [(package, Term::Negative(range))] => self.term(package, range, Phrase::Required),
+            [
+                (ResolverPackage::Package(p1), Term::Positive(_)),
+                (p2, Term::Positive(range)),
+            ]
+            | [
+                (p2, Term::Positive(range)),
+                (ResolverPackage::Package(p1), Term::Positive(_)),
+            ] if *p1 == self.0.root => {
+                format!("your project cannot use {}", self.package(p2, range))
+            }
+            [(p1, Term::Positive(r1)), (p2, Term::Negative(r2))]
+            | [(p2, Term::Negative(r2)), (p1, Term::Positive(r1))] => self.format_external(
+                &External::FromDependencyOf((*p1).clone(), r1.clone(), (*p2).clone(), r2.clone()),
+            ),
+            terms => {
+                let mut terms = terms
+                    .iter()
+                    .map(|(package, term)| match term {
+                        Term::Positive(range) => self.package(package, range),
+                        Term::Negative(range) => self.package(package, &range.complement()),
+                    })
+                    .collect::<Vec<_>>();
+                let last = terms.pop().unwrap_or_default();
+                format!("{} and {last} cannot be used together", terms.join(", "))
+            }
+        }
+    }
+
+    fn explain_both_external(
+        &self,
+        external1: &Incompatibility,
+        external2: &Incompatibility,
+        current_terms: &Terms,
+    ) -> String {
+        format!(
+            "Because {}, {}.",
+            self.join(external1, external2),
+            self.format_terms(current_terms)
+        )
+    }
+
+    fn explain_both_ref(
+        &self,
+        ref_id1: usize,
+        derived1: &Derived<ResolverPackage, Range, String>,
+        ref_id2: usize,
+        derived2: &Derived<ResolverPackage, Range, String>,
+        current_terms: &Terms,
+    ) -> String {
+        format!(
+            "Because {} ({ref_id1}) and {} ({ref_id2}), {}.",
+            self.format_terms(&derived1.terms),
+            self.format_terms(&derived2.terms),
+            self.format_terms(current_terms)
+        )
+    }
+
+    fn explain_ref_and_external(
+        &self,
+        ref_id: usize,
+        derived: &Derived<ResolverPackage, Range, String>,
+        external: &Incompatibility,
+        current_terms: &Terms,
+    ) -> String {
+        format!(
+            "Because {} ({ref_id}) and {}, {}.",
+            self.format_terms(&derived.terms),
+            self.format_external(external),
+            self.format_terms(current_terms)
+        )
+    }
+
+    fn and_explain_external(&self, external: &Incompatibility, current_terms: &Terms) -> String {
+        format!(
+            "And because {}, {}.",
+            self.format_external(external),
+            self.format_terms(current_terms)
+        )
+    }
+
+    fn and_explain_ref(
+        &self,
+        ref_id: usize,
+        derived: &Derived<ResolverPackage, Range, String>,
+        current_terms: &Terms,
+    ) -> String {
+        format!(
+            "And because {} ({ref_id}), {}.",
+            self.format_terms(&derived.terms),
+            self.format_terms(current_terms)
+        )
+    }
+
+    fn and_explain_prior_and_external(
+        &self,
+        prior_external: &Incompatibility,
+        external: &Incompatibility,
+        current_terms: &Terms,
+    ) -> String {
+        format!(
+            "And because {}, {}.",
+            self.join(prior_external, external),
+            self.format_terms(current_terms)
+        )
+    }
+}
+
+/// Write a range using the Hex requirement syntax, i.e. `>= 1.0.0 and < 2.0.0`.
+pub(super) fn hex_range(range: &Range) -> String {
+    if range.is_empty() {
+        return "no version".into();
+    }
+    range
+        .iter()
+        .map(|segment| match segment {
+            (Bound::Included(low), Bound::Included(high)) if low == high => format!("== {low}"),
+            (Bound::Unbounded, Bound::Unbounded) => ">= 0.0.0".into(),
+            (low, high) => [lower_bound(low), upper_bound(high)]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" and "),
+        })
+        .collect::<Vec<_>>()
+        .join(" or ")
+}
+
+fn lower_bound(bound: &Bound<Version>) -> Option<String> {
+    match bound {
+        Bound::Included(version) => Some(format!(">= {version}")),
+        Bound::Excluded(version) => Some(format!("> {version}")),
+        Bound::Unbounded => None,
+    }
+}
+
+fn upper_bound(bound: &Bound<Version>) -> Option<String> {
+    match bound {
+        Bound::Included(version) => Some(format!("<= {version}")),
+        Bound::Excluded(version) => Some(format!("< {version}")),
+        Bound::Unbounded => None,
+    }
+}
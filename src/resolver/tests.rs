@@ -0,0 +1,495 @@
+use super::*;
+use crate::{RetirementReason, RetirementStatus};
+
+fn release(version: &str, requirements: &[(&str, &str)]) -> Release<()> {
+    Release {
+        version: Version::parse(version).unwrap(),
+        requirements: requirements
+            .iter()
+            .map(|(name, requirement)| {
+                let dependency = Dependency {
+                    requirement: Range::new(requirement.to_string()).unwrap(),
+                    optional: false,
+                    app: None,
+                    repository: None,
+                };
+                (name.to_string(), dependency)
+            })
+            .collect(),
+        retirement_status: None,
+        inner_checksum: vec![],
+        outer_checksum: vec![],
+        meta: (),
+    }
+}
+
+fn retired(version: &str) -> Release<()> {
+    let mut release = release(version, &[]);
+    release.retirement_status = Some(RetirementStatus {
+        reason: RetirementReason::Security,
+        message: "".into(),
+    });
+    release
+}
+
+fn optional(mut release: Release<()>) -> Release<()> {
+    for dependency in release.requirements.values_mut() {
+        dependency.optional = true;
+    }
+    release
+}
+
+fn from_repository(mut release: Release<()>, repository: &str) -> Release<()> {
+    for dependency in release.requirements.values_mut() {
+        dependency.repository = Some(repository.into());
+    }
+    release
+}
+
+fn remote(packages: Vec<(&str, Vec<Release<()>>)>) -> HashMap<String, Package> {
+    packages
+        .into_iter()
+        .map(|(name, releases)| {
+            let package = Package {
+                name: name.to_string(),
+                repository: "hexpm".into(),
+                releases,
+            };
+            (name.to_string(), package)
+        })
+        .collect()
+}
+
+fn requirements(requirements: &[(&str, &str)]) -> Vec<(String, Range)> {
+    requirements
+        .iter()
+        .map(|(name, requirement)| {
+            let range = Range::new(requirement.to_string()).unwrap();
+            (name.to_string(), range)
+        })
+        .collect()
+}
+
+fn versions(versions: &[(&str, &str)]) -> HashMap<String, Version> {
+    versions
+        .iter()
+        .map(|(name, version)| (name.to_string(), Version::parse(version).unwrap()))
+        .collect()
+}
+
+fn resolve(
+    remote: &HashMap<String, Package>,
+    requirements_: &[(&str, &str)],
+    locked: &[(&str, &str)],
+) -> Result<HashMap<String, Version>, ResolutionError> {
+    resolve_versions(
+        remote,
+        "app",
+        requirements(requirements_),
+        &versions(locked),
+    )
+}
+
+fn example_remote() -> HashMap<String, Package> {
+    remote(vec![
+        (
+            "gleam_stdlib",
+            vec![
+                release("0.1.0", &[]),
+                release("0.2.0", &[]),
+                release("0.2.2", &[]),
+                release("0.3.0", &[]),
+            ],
+        ),
+        (
+            "gleam_otp",
+            vec![
+                release("0.1.0", &[("gleam_stdlib", ">= 0.1.0")]),
+                release("0.2.0", &[("gleam_stdlib", "~> 0.2")]),
+                release("0.3.0-rc1", &[("gleam_stdlib", "~> 0.3")]),
+            ],
+        ),
+    ])
+}
+
+#[test]
+fn resolution_with_no_requirements() {
+    let result = resolve(&example_remote(), &[], &[]).unwrap();
+    assert_eq!(result, HashMap::new());
+}
+
+#[test]
+fn resolution_picks_highest_versions() {
+    let result = resolve(&example_remote(), &[("gleam_otp", "~> 0.1")], &[]).unwrap();
+    assert_eq!(
+        result,
+        versions(&[("gleam_otp", "0.2.0"), ("gleam_stdlib", "0.3.0")])
+    );
+}
+
+#[test]
+fn resolution_transitive_constraint() {
+    let result = resolve(
+        &example_remote(),
+        &[("gleam_otp", "~> 0.2.0"), ("gleam_stdlib", "< 0.3.0")],
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        result,
+        versions(&[("gleam_otp", "0.2.0"), ("gleam_stdlib", "0.2.2")])
+    );
+}
+
+#[test]
+fn resolution_keeps_locked_versions() {
+    let result = resolve(
+        &example_remote(),
+        &[("gleam_otp", "~> 0.1")],
+        &[("gleam_stdlib", "0.2.0")],
+    )
+    .unwrap();
+    assert_eq!(
+        result,
+        versions(&[("gleam_otp", "0.2.0"), ("gleam_stdlib", "0.2.0")])
+    );
+}
+
+#[test]
+fn resolution_locked_version_conflict() {
+    let error = resolve(
+        &example_remote(),
+        &[("gleam_stdlib", "~> 0.3")],
+        &[("gleam_stdlib", "0.2.0")],
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "gleam_stdlib is specified with the requirement `~> 0.3`, \
+but it is locked to 0.2.0, which is incompatible"
+    );
+}
+
+#[test]
+fn resolution_skips_pre_releases() {
+    let result = resolve(&example_remote(), &[("gleam_otp", ">= 0.1.0")], &[]).unwrap();
+    assert_eq!(result["gleam_otp"], Version::new(0, 2, 0));
+}
+
+#[test]
+fn resolution_pre_release_can_be_requested() {
+    let result = resolve(&example_remote(), &[("gleam_otp", "~> 0.3.0-rc1")], &[]).unwrap();
+    assert_eq!(result["gleam_otp"], Version::parse("0.3.0-rc1").unwrap());
+}
+
+#[test]
+fn resolution_pre_release_can_be_locked() {
+    let result = resolve(
+        &example_remote(),
+        &[("gleam_otp", ">= 0.1.0")],
+        &[("gleam_otp", "0.3.0-rc1")],
+    )
+    .unwrap();
+    assert_eq!(result["gleam_otp"], Version::parse("0.3.0-rc1").unwrap());
+}
+
+#[test]
+fn resolution_skips_retired_releases() {
+    let remote = remote(vec![(
+        "gleam_stdlib",
+        vec![release("0.1.0", &[]), retired("0.2.0")],
+    )]);
+    let result = resolve(&remote, &[("gleam_stdlib", "~> 0.1")], &[]).unwrap();
+    assert_eq!(result, versions(&[("gleam_stdlib", "0.1.0")]));
+}
+
+#[test]
+fn resolution_retired_release_can_be_locked() {
+    let remote = remote(vec![(
+        "gleam_stdlib",
+        vec![release("0.1.0", &[]), retired("0.2.0")],
+    )]);
+    let result = resolve(
+        &remote,
+        &[("gleam_stdlib", "~> 0.1")],
+        &[("gleam_stdlib", "0.2.0")],
+    )
+    .unwrap();
+    assert_eq!(result, versions(&[("gleam_stdlib", "0.2.0")]));
+}
+
+#[test]
+fn resolution_only_retired_releases() {
+    let remote = remote(vec![("gleam_stdlib", vec![retired("0.1.0")])]);
+    let error = resolve(&remote, &[("gleam_stdlib", "~> 0.1")], &[]).unwrap_err();
+    assert!(matches!(error, ResolutionError::NoSolution(_)));
+}
+
+#[test]
+fn resolution_ignores_unneeded_optional_dependencies() {
+    let remote = remote(vec![
+        (
+            "json",
+            vec![optional(release("1.0.0", &[("decoder", "~> 1.0")]))],
+        ),
+        ("decoder", vec![release("1.0.0", &[])]),
+    ]);
+    let result = resolve(&remote, &[("json", "~> 1.0")], &[]).unwrap();
+    assert_eq!(result, versions(&[("json", "1.0.0")]));
+}
+
+#[test]
+fn resolution_applies_optional_requirements_when_needed() {
+    let remote = remote(vec![
+        (
+            "json",
+            vec![optional(release("1.0.0", &[("decoder", "< 1.1.0")]))],
+        ),
+        (
+            "decoder",
+            vec![release("1.0.0", &[]), release("1.1.0", &[])],
+        ),
+    ]);
+    let result = resolve(&remote, &[("json", "~> 1.0"), ("decoder", "~> 1.0")], &[]).unwrap();
+    assert_eq!(result, versions(&[("json", "1.0.0"), ("decoder", "1.0.0")]));
+}
+
+#[test]
+fn resolution_drops_optional_requirements_of_rejected_versions() {
+    // json 1.1.0 is tried first and rejected as no compatible version of
+    // parser exists, so its optional requirement on decoder must not apply.
+    let mut json = optional(release("1.1.0", &[("decoder", "< 1.1.0")]));
+    json.requirements
+        .extend(release("1.1.0", &[("parser", "~> 2.0")]).requirements);
+    let remote = remote(vec![
+        ("json", vec![release("1.0.0", &[]), json]),
+        (
+            "http",
+            vec![
+                release("1.0.0", &[("decoder", "~> 1.0")]),
+                release("1.1.0", &[("decoder", "~> 1.0")]),
+                release("1.2.0", &[("decoder", "~> 1.0")]),
+            ],
+        ),
+        (
+            "decoder",
+            vec![release("1.0.0", &[]), release("1.1.0", &[])],
+        ),
+        ("parser", vec![release("1.0.0", &[])]),
+    ]);
+    let result = resolve(&remote, &[("json", "~> 1.0"), ("http", "~> 1.0")], &[]).unwrap();
+    assert_eq!(
+        result,
+        versions(&[("json", "1.0.0"), ("http", "1.2.0"), ("decoder", "1.1.0")])
+    );
+}
+
+#[test]
+fn resolution_optional_requirement_conflict() {
+    let remote = remote(vec![
+        (
+            "json",
+            vec![optional(release("1.0.0", &[("decoder", "~> 1.0")]))],
+        ),
+        (
+            "decoder",
+            vec![release("1.0.0", &[]), release("2.0.0", &[])],
+        ),
+    ]);
+    let error = resolve(&remote, &[("json", "~> 1.0"), ("decoder", "~> 2.0")], &[]).unwrap_err();
+    assert_eq!(
+        error.explanation(),
+        "Because json 1.0.0 optionally requires decoder ~> 1.0 \
+but your project requires decoder ~> 2.0, your project cannot use json 1.0.0.
+And because your project requires json ~> 1.0, \
+your project's requirements cannot be satisfied."
+    );
+}
+
+#[test]
+fn resolution_optional_requirement_conflict_between_packages() {
+    let remote = remote(vec![
+        (
+            "json",
+            vec![
+                optional(release("1.0.0", &[("decoder", "~> 1.0")])),
+                optional(release("1.1.0", &[("decoder", "~> 1.0")])),
+            ],
+        ),
+        ("http", vec![release("1.0.0", &[("decoder", "~> 2.0")])]),
+        (
+            "decoder",
+            vec![release("1.0.0", &[]), release("2.0.0", &[])],
+        ),
+    ]);
+    let error = resolve(&remote, &[("json", "~> 1.0"), ("http", "~> 1.0")], &[]).unwrap_err();
+    assert_eq!(
+        error.explanation(),
+        "Because json (== 1.0.0 or == 1.1.0) optionally requires decoder ~> 1.0 \
+but http 1.0.0 requires decoder ~> 2.0, \
+json ~> 1.0 and http ~> 1.0 cannot be used together.
+And because your project requires json ~> 1.0 and your project requires http ~> 1.0, \
+your project's requirements cannot be satisfied."
+    );
+}
+
+#[test]
+fn resolution_conflict() {
+    let error = resolve(
+        &example_remote(),
+        &[("gleam_otp", "~> 0.2.0"), ("gleam_stdlib", "~> 0.1.0")],
+        &[],
+    )
+    .unwrap_err();
+    assert!(matches!(error, ResolutionError::NoSolution(_)));
+    assert_eq!(
+        error.explanation(),
+        "Because gleam_otp ~> 0.2.0 requires gleam_stdlib ~> 0.2 \
+and your project requires gleam_otp ~> 0.2.0, \
+your project requires gleam_stdlib ~> 0.2.
+And because your project requires gleam_stdlib ~> 0.1.0, \
+your project's requirements cannot be satisfied."
+    );
+}
+
+#[test]
+fn resolution_conflict_with_locked_version() {
+    let error = resolve(
+        &example_remote(),
+        &[("gleam_otp", "~> 0.2.0")],
+        &[("gleam_stdlib", "0.1.0")],
+    )
+    .unwrap_err();
+    assert!(
+        error
+            .explanation()
+            .contains("because your project requires gleam_stdlib 0.1.0,")
+    );
+}
+
+#[test]
+fn resolution_conflict_between_dependencies() {
+    let remote = remote(vec![
+        ("json", vec![release("1.0.0", &[("decoder", "~> 1.0")])]),
+        ("http", vec![release("1.0.0", &[("decoder", "~> 2.0")])]),
+        (
+            "decoder",
+            vec![release("1.0.0", &[]), release("2.0.0", &[])],
+        ),
+    ]);
+    let error = resolve(&remote, &[("json", "~> 1.0"), ("http", "~> 1.0")], &[]).unwrap_err();
+    assert_eq!(
+        error.explanation().lines().next().unwrap(),
+        "Because json ~> 1.0 requires decoder ~> 1.0 \
+but http 1.0.0 requires decoder ~> 2.0, \
+json ~> 1.0 and http 1.0.0 cannot be used together."
+    );
+}
+
+#[test]
+fn resolution_missing_package() {
+    let error = resolve(&example_remote(), &[("unknown", "~> 1.0")], &[]).unwrap_err();
+    assert!(matches!(error, ResolutionError::NoSolution(_)));
+    assert_eq!(
+        error.explanation(),
+        "Because no package named unknown exists and your project requires unknown ~> 1.0, \
+your project's requirements cannot be satisfied."
+    );
+}
+
+/// A fetcher that records the repository each package is fetched from.
+struct RecordingFetcher {
+    packages: HashMap<String, Package>,
+    fetched: RefCell<Vec<(String, Option<String>)>>,
+}
+
+impl PackageFetcher for RecordingFetcher {
+    fn get_package(&self, name: &str, repository: Option<&str>) -> Result<Package, ApiError> {
+        self.fetched
+            .borrow_mut()
+            .push((name.to_string(), repository.map(String::from)));
+        self.packages.get_package(name, repository)
+    }
+}
+
+#[test]
+fn resolution_fetches_from_dependency_repositories() {
+    let mut utils = from_repository(release("1.0.0", &[("decoder", "~> 1.0")]), "hexpm");
+    utils
+        .requirements
+        .extend(release("1.0.0", &[("base", "~> 1.0")]).requirements);
+    let fetcher = RecordingFetcher {
+        packages: remote(vec![
+            (
+                "json",
+                vec![from_repository(
+                    release("1.0.0", &[("utils", "~> 1.0")]),
+                    "hexpm:my_org",
+                )],
+            ),
+            ("utils", vec![utils]),
+            ("base", vec![release("1.0.0", &[])]),
+            ("decoder", vec![release("1.0.0", &[])]),
+        ]),
+        fetched: RefCell::new(vec![]),
+    };
+    let _ = resolve_versions(
+        &fetcher,
+        "app",
+        requirements(&[("json", "~> 1.0")]),
+        &HashMap::new(),
+    )
+    .unwrap();
+
+    let mut fetched = fetcher.fetched.take();
+    fetched.sort();
+    let repository = |name: &str| Some(name.to_string());
+    assert_eq!(
+        fetched,
+        vec![
+            // Dependencies without a repository use that of their dependent.
+            ("base".to_string(), repository("hexpm:my_org")),
+            ("decoder".to_string(), repository("hexpm")),
+            ("json".to_string(), None),
+            ("utils".to_string(), repository("hexpm:my_org")),
+        ]
+    );
+}
+
+struct FailingFetcher;
+
+impl PackageFetcher for FailingFetcher {
+    fn get_package(&self, _name: &str, _repository: Option<&str>) -> Result<Package, ApiError> {
+        Err(ApiError::RateLimited {
+            rate_limit: None,
+            retry_after: None,
+            body: None,
+        })
+    }
+}
+
+#[test]
+fn resolution_fetch_error() {
+    let error = resolve_versions(
+        &FailingFetcher,
+        "app",
+        requirements(&[("gleam_stdlib", "~> 1.0")]),
+        &HashMap::new(),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        error,
+        ResolutionError::Fetch(name, ApiError::RateLimited { .. }) if name == "gleam_stdlib"
+    ));
+}
+
+#[test]
+fn hex_range_syntax() {
+    let range = |spec: &str| Range::new(spec.to_string()).unwrap().to_pubgrub().clone();
+    assert_eq!(report::hex_range(&range("~> 1.2")), ">= 1.2.0 and < 2.0.0");
+    assert_eq!(report::hex_range(&range("== 1.0.0")), "== 1.0.0");
+    assert_eq!(
+        report::hex_range(&range("< 1.0.0 or >= 2.0.0")),
+        "< 1.0.0 or >= 2.0.0"
+    );
+}
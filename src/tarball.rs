@@ -0,0 +1,338 @@
+//! Construction and reading of Hex package tarballs, as uploaded with
+//! [`api_publish_package_request`](crate::api_publish_package_request) and
+//! downloaded with
+//! [`repository_get_package_tarball_request`](crate::repository_get_package_tarball_request).
+//!
+//! A package tarball is an uncompressed tar file containing these files:
+//!
+//! - `VERSION`: the tarball format version, always `3`.
+//! - `metadata.config`: the package metadata in Erlang `file:consult` format.
+//! - `contents.tar.gz`: a gzipped tarball of the package's files.
+//! - `CHECKSUM`: the hex encoded SHA-256 of the above three files.
+//!
+//! https://github.com/hexpm/specifications/blob/main/package_tarball.md
+
+mod metadata;
+#[cfg(test)]
+mod tests;
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Component, Path};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use ring::digest::{SHA256, digest};
+use thiserror::Error;
+
+use crate::Release;
+
+pub use self::metadata::PackageMetadata;
+
+/// The version of the tarball format produced and read by this module.
+pub const TARBALL_VERSION: &str = "3";
+
+/// The largest uncompressed package contents that will be read, matching the
+/// limit used by Hex.
+pub const CONTENTS_MAX_UNCOMPRESSED_SIZE: u64 = 128 * 1024 * 1024;
+
+/// The modification time Hex uses for every tarball entry, 2000-01-01T00:00:00Z.
+const ENTRY_MTIME: u64 = 946_684_800;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("the given package name {0} is not valid")]
+    InvalidPackageNameFormat(String),
+
+    #[error("the file path {0} is not valid in a package tarball")]
+    InvalidPath(String),
+
+    #[error("the package tarball entry {0} is a link or special file")]
+    UnsupportedEntry(String),
+
+    #[error("the package tarball does not contain the {0} file")]
+    MissingFile(&'static str),
+
+    #[error("the package tarball format version {0} is not supported")]
+    UnsupportedVersion(String),
+
+    #[error("the package tarball did not have the expected checksum")]
+    IncorrectChecksum,
+
+    #[error("the package metadata is not valid: {0}")]
+    InvalidMetadata(String),
+
+    #[error("the package contents exceed the maximum uncompressed size")]
+    ContentsTooLarge,
+}
+
+/// A built package tarball.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Tarball {
+    /// The outer tarball, to be uploaded with
+    /// [`api_publish_package_request`](crate::api_publish_package_request).
+    pub data: Vec<u8>,
+    /// sha256 checksum of the `VERSION`, `metadata.config` and
+    /// `contents.tar.gz` files, as written to the `CHECKSUM` file.
+    pub inner_checksum: Vec<u8>,
+    /// sha256 checksum of the outer tarball.
+    pub outer_checksum: Vec<u8>,
+}
+
+/// Builds a Hex package tarball from package metadata and a set of files.
+///
+/// ```
+/// use hexpm::{tarball::{PackageMetadata, TarballBuilder}, version::Version};
+///
+/// let mut metadata = PackageMetadata::new("my_package", Version::new(1, 0, 0));
+/// metadata.description = "A very fine package".into();
+/// metadata.build_tools = vec!["gleam".into()];
+///
+/// let tarball = TarballBuilder::new(metadata)
+///     .add_file("src/my_package.gleam", "pub fn main() { Nil }")
+///     .add_file("gleam.toml", "name = \"my_package\"")
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(tarball.outer_checksum.len(), 32);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TarballBuilder {
+    metadata: PackageMetadata,
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl TarballBuilder {
+    pub fn new(metadata: PackageMetadata) -> Self {
+        Self {
+            metadata,
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Add a file to the package contents. The path must be relative and
+    /// use `/` as the separator. Adding a path a second time replaces the
+    /// previously added contents.
+    pub fn add_file(&mut self, path: impl Into<String>, contents: impl Into<Vec<u8>>) -> &mut Self {
+        let _ = self.files.insert(path.into(), contents.into());
+        self
+    }
+
+    /// Produce the outer tarball along with its inner and outer checksums.
+    pub fn build(&self) -> Result<Tarball, Error> {
+        if !crate::is_valid_package_name(&self.metadata.name) {
+            return Err(Error::InvalidPackageNameFormat(self.metadata.name.clone()));
+        }
+        for path in self.files.keys() {
+            validate_path(path)?;
+        }
+
+        let mut metadata = self.metadata.clone();
+        metadata.files = self.files.keys().cloned().collect();
+        let metadata = metadata.to_config().into_bytes();
+        let contents = self.contents_tarball()?;
+
+        let mut inner = Vec::with_capacity(TARBALL_VERSION.len() + metadata.len() + contents.len());
+        inner.extend_from_slice(TARBALL_VERSION.as_bytes());
+        inner.extend_from_slice(&metadata);
+        inner.extend_from_slice(&contents);
+        let inner_checksum = digest(&SHA256, &inner).as_ref().to_vec();
+        let checksum = base16::encode_upper(&inner_checksum);
+
+        let mut tarball = tar::Builder::new(Vec::new());
+        append_entry(&mut tarball, "VERSION", TARBALL_VERSION.as_bytes())?;
+        append_entry(&mut tarball, "CHECKSUM", checksum.as_bytes())?;
+        append_entry(&mut tarball, "metadata.config", &metadata)?;
+        append_entry(&mut tarball, "contents.tar.gz", &contents)?;
+        let data = tarball.into_inner()?;
+        let outer_checksum = digest(&SHA256, &data).as_ref().to_vec();
+
+        Ok(Tarball {
+            data,
+            inner_checksum,
+            outer_checksum,
+        })
+    }
+
+    fn contents_tarball(&self) -> Result<Vec<u8>, Error> {
+        let encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let mut tarball = tar::Builder::new(encoder);
+        for (path, contents) in &self.files {
+            append_entry(&mut tarball, path, contents)?;
+        }
+        let mut encoder = tarball.into_inner()?;
+        encoder.flush()?;
+        Ok(encoder.finish()?)
+    }
+}
+
+pub(crate) fn append_entry<W: Write>(
+    tarball: &mut tar::Builder<W>,
+    path: &str,
+    contents: &[u8],
+) -> std::io::Result<()> {
+    let mut header = tar::Header::new_ustar();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(ENTRY_MTIME);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_entry_type(tar::EntryType::Regular);
+    tarball.append_data(&mut header, path, contents)
+}
+
+fn validate_path(path: &str) -> Result<(), Error> {
+    if is_valid_path(path) {
+        Ok(())
+    } else {
+        Err(Error::InvalidPath(path.to_string()))
+    }
+}
+
+/// Whether a path is relative, uses `/` as the separator, and stays within
+/// the directory it is unpacked into.
+pub(crate) fn is_valid_path(path: &str) -> bool {
+    !path.is_empty()
+        && !path.contains('\\')
+        && Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// A file from the contents of a package tarball.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ContentsEntry {
+    /// The relative path of the file, using `/` as the separator.
+    pub path: String,
+    pub contents: Vec<u8>,
+}
+
+/// A package tarball that has been unpacked and checked for integrity.
+///
+/// ```no_run
+/// # fn download(_: &str) -> Vec<u8> { unimplemented!() }
+/// use hexpm::tarball::ReleaseArchive;
+///
+/// let archive = ReleaseArchive::parse(&download("gleam_stdlib-0.14.0.tar")).unwrap();
+/// for entry in archive.contents() {
+///     println!("{}: {} bytes", entry.path, entry.contents.len());
+/// }
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ReleaseArchive {
+    /// The contents of the `VERSION` file.
+    pub tarball_version: String,
+    pub metadata: PackageMetadata,
+    /// sha256 checksum of the `VERSION`, `metadata.config` and
+    /// `contents.tar.gz` files, verified against the `CHECKSUM` file.
+    pub inner_checksum: Vec<u8>,
+    /// sha256 checksum of the outer tarball.
+    pub outer_checksum: Vec<u8>,
+    contents: Vec<ContentsEntry>,
+}
+
+impl ReleaseArchive {
+    /// Unpack an outer package tarball, verifying the inner checksum in the
+    /// `CHECKSUM` file and rejecting any contents that are links or that have
+    /// paths that would escape the directory they are unpacked into.
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        let mut version = None;
+        let mut checksum = None;
+        let mut metadata = None;
+        let mut contents = None;
+
+        for entry in tar::Archive::new(data).entries()? {
+            let mut entry = entry?;
+            let slot = match entry.path_bytes().as_ref() {
+                b"VERSION" => &mut version,
+                b"CHECKSUM" => &mut checksum,
+                b"metadata.config" => &mut metadata,
+                b"contents.tar.gz" => &mut contents,
+                _ => continue,
+            };
+            let mut buffer = Vec::new();
+            let _ = entry.read_to_end(&mut buffer)?;
+            *slot = Some(buffer);
+        }
+
+        let version = version.ok_or(Error::MissingFile("VERSION"))?;
+        let checksum = checksum.ok_or(Error::MissingFile("CHECKSUM"))?;
+        let metadata = metadata.ok_or(Error::MissingFile("metadata.config"))?;
+        let contents = contents.ok_or(Error::MissingFile("contents.tar.gz"))?;
+
+        let tarball_version = String::from_utf8_lossy(&version).trim().to_string();
+        if tarball_version != TARBALL_VERSION {
+            return Err(Error::UnsupportedVersion(tarball_version));
+        }
+
+        let mut inner = Vec::with_capacity(version.len() + metadata.len() + contents.len());
+        inner.extend_from_slice(&version);
+        inner.extend_from_slice(&metadata);
+        inner.extend_from_slice(&contents);
+        let inner_checksum = digest(&SHA256, &inner).as_ref().to_vec();
+        let expected = base16::decode(String::from_utf8_lossy(&checksum).trim())
+            .map_err(|_| Error::IncorrectChecksum)?;
+        if expected != inner_checksum {
+            return Err(Error::IncorrectChecksum);
+        }
+
+        let metadata =
+            std::str::from_utf8(&metadata).map_err(|e| Error::InvalidMetadata(e.to_string()))?;
+
+        Ok(Self {
+            tarball_version,
+            metadata: PackageMetadata::from_config(metadata)?,
+            inner_checksum,
+            outer_checksum: digest(&SHA256, data).as_ref().to_vec(),
+            contents: unpack_contents(&contents)?,
+        })
+    }
+
+    /// Check the archive against the checksums a registry holds for the
+    /// release, as returned by
+    /// [`repository_v2_get_package_response`](crate::repository_v2_get_package_response).
+    /// Checksums that are absent from the release are not checked.
+    pub fn verify<Meta>(&self, release: &Release<Meta>) -> Result<(), Error> {
+        let matches = |expected: &[u8], actual: &[u8]| expected.is_empty() || expected == actual;
+        if matches(&release.inner_checksum, &self.inner_checksum)
+            && matches(&release.outer_checksum, &self.outer_checksum)
+        {
+            Ok(())
+        } else {
+            Err(Error::IncorrectChecksum)
+        }
+    }
+
+    /// The files of the package, in the order they appear in the tarball.
+    pub fn contents(&self) -> std::slice::Iter<'_, ContentsEntry> {
+        self.contents.iter()
+    }
+}
+
+fn unpack_contents(gzipped: &[u8]) -> Result<Vec<ContentsEntry>, Error> {
+    let mut tarball = Vec::new();
+    let _ = GzDecoder::new(gzipped)
+        .take(CONTENTS_MAX_UNCOMPRESSED_SIZE + 1)
+        .read_to_end(&mut tarball)?;
+    if tarball.len() as u64 > CONTENTS_MAX_UNCOMPRESSED_SIZE {
+        return Err(Error::ContentsTooLarge);
+    }
+
+    let mut entries = Vec::new();
+    for entry in tar::Archive::new(tarball.as_slice()).entries()? {
+        let mut entry = entry?;
+        let path = String::from_utf8_lossy(&entry.path_bytes()).to_string();
+        validate_path(path.trim_end_matches('/'))?;
+        match entry.header().entry_type() {
+            tar::EntryType::Regular | tar::EntryType::Continuous => (),
+            tar::EntryType::Directory => continue,
+            _ => return Err(Error::UnsupportedEntry(path)),
+        }
+        let mut contents = Vec::new();
+        let _ = entry.read_to_end(&mut contents)?;
+        entries.push(ContentsEntry { path, contents });
+    }
+    Ok(entries)
+}
@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use super::Error;
+use crate::Dependency;
+use crate::consult::{self, Term};
+use crate::version::{Range, Version};
+
+/// The information about a package release stored in the `metadata.config`
+/// file of a package tarball.
+///
+/// ```
+/// use hexpm::tarball::PackageMetadata;
+///
+/// let metadata = PackageMetadata::from_config(
+///     r#"{<<"name">>,<<"my_package">>}.
+///        {<<"app">>,<<"my_package">>}.
+///        {<<"version">>,<<"1.0.0">>}."#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(metadata.name, "my_package");
+/// assert_eq!(PackageMetadata::from_config(&metadata.to_config()).unwrap(), metadata);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PackageMetadata {
+    pub name: String,
+    pub version: Version,
+    /// The OTP application name, if not set the package name is used.
+    pub app: Option<String>,
+    pub description: String,
+    pub licenses: Vec<String>,
+    /// Named links, i.e. `("Repository", "https://github.com/gleam-lang/stdlib")`.
+    pub links: Vec<(String, String)>,
+    pub requirements: HashMap<String, Dependency>,
+    /// The build tools able to compile the package, i.e. `mix`, `rebar3` or `gleam`.
+    pub build_tools: Vec<String>,
+    /// The paths of the files in the package contents. When building a
+    /// tarball with [`TarballBuilder`](super::TarballBuilder) this is replaced
+    /// with the files added to the builder.
+    pub files: Vec<String>,
+}
+
+impl PackageMetadata {
+    pub fn new(name: impl Into<String>, version: Version) -> Self {
+        Self {
+            name: name.into(),
+            version,
+            app: None,
+            description: String::new(),
+            licenses: vec![],
+            links: vec![],
+            requirements: HashMap::new(),
+            build_tools: vec![],
+            files: vec![],
+        }
+    }
+
+    /// Parse the contents of a `metadata.config` file.
+    pub fn from_config(config: &str) -> Result<Self, Error> {
+        let terms = consult::parse(config).map_err(|e| Error::InvalidMetadata(e.to_string()))?;
+        Self::from_terms(&terms)
+    }
+
+    /// Render the metadata in the format of a `metadata.config` file.
+    pub fn to_config(&self) -> String {
+        consult::to_string(&self.to_terms()).expect("metadata has no floats")
+    }
+
+    /// Convert `{Key, Value}` terms, as found in a `metadata.config` file,
+    /// into metadata. Unknown keys are ignored, and a name that is not a valid
+    /// package name is rejected as it may be used to construct file paths.
+    pub fn from_terms(terms: &[Term]) -> Result<Self, Error> {
+        let mut fields = HashMap::new();
+        for term in terms {
+            match term.as_tuple() {
+                Some([key, value]) => {
+                    let key = key.as_binary_str().ok_or_else(|| invalid("key"))?;
+                    let _ = fields.insert(key, value);
+                }
+                _ => return Err(invalid("entry")),
+            }
+        }
+
+        let string = |key: &'static str| -> Result<Option<String>, Error> {
+            fields
+                .get(key)
+                .map(|term| term.as_binary_str().map(String::from).ok_or(invalid(key)))
+                .transpose()
+        };
+        let strings = |key: &'static str| -> Result<Vec<String>, Error> {
+            let Some(term) = fields.get(key) else {
+                return Ok(vec![]);
+            };
+            term.as_list()
+                .ok_or(invalid(key))?
+                .iter()
+                .map(|term| term.as_binary_str().map(String::from).ok_or(invalid(key)))
+                .collect()
+        };
+
+        let name = string("name")?.ok_or(invalid("name"))?;
+        if !crate::is_valid_package_name(&name) {
+            return Err(Error::InvalidPackageNameFormat(name));
+        }
+        let version = string("version")?.ok_or(invalid("version"))?;
+        let version = Version::parse(&version).map_err(|_| invalid("version"))?;
+
+        let links = match fields.get("links") {
+            None => vec![],
+            Some(term) => proplist(term)
+                .ok_or(invalid("links"))?
+                .into_iter()
+                .map(|(name, url)| match url.as_binary_str() {
+                    Some(url) => Ok((name.to_string(), url.to_string())),
+                    None => Err(invalid("links")),
+                })
+                .collect::<Result<_, _>>()?,
+        };
+
+        let requirements = match fields.get("requirements") {
+            None => HashMap::new(),
+            Some(term) => requirements(term).ok_or(invalid("requirements"))??,
+        };
+
+        Ok(Self {
+            name,
+            version,
+            app: string("app")?,
+            description: string("description")?.unwrap_or_default(),
+            licenses: strings("licenses")?,
+            links,
+            requirements,
+            build_tools: strings("build_tools")?,
+            files: strings("files")?,
+        })
+    }
+
+    /// Convert the metadata into `{Key, Value}` terms, sorted by key so the
+    /// output is deterministic.
+    pub fn to_terms(&self) -> Vec<Term> {
+        let app = self.app.as_deref().unwrap_or(&self.name);
+        let binaries = |values: &[String]| {
+            Term::List(values.iter().map(|value| Term::binary(value)).collect())
+        };
+
+        let mut requirements = self.requirements.iter().collect::<Vec<_>>();
+        requirements.sort_by_key(|(name, _)| *name);
+        let requirements = requirements
+            .into_iter()
+            .map(|(name, dependency)| {
+                let app = dependency.app.as_deref().unwrap_or(name);
+                let optional = if dependency.optional { "true" } else { "false" };
+                let mut properties = vec![
+                    pair("app", Term::binary(app)),
+                    pair("optional", Term::Atom(optional.into())),
+                    pair("requirement", Term::binary(dependency.requirement.as_str())),
+                ];
+                if let Some(repository) = &dependency.repository {
+                    properties.push(pair("repository", Term::binary(repository)));
+                }
+                pair(name, Term::List(properties))
+            })
+            .collect();
+
+        let links = self
+            .links
+            .iter()
+            .map(|(name, url)| pair(name, Term::binary(url)))
+            .collect();
+
+        vec![
+            pair("app", Term::binary(app)),
+            pair("build_tools", binaries(&self.build_tools)),
+            pair("description", Term::binary(&self.description)),
+            pair("files", binaries(&self.files)),
+            pair("licenses", binaries(&self.licenses)),
+            pair("links", Term::List(links)),
+            pair("name", Term::binary(&self.name)),
+            pair("requirements", Term::List(requirements)),
+            pair("version", Term::binary(&self.version.to_string())),
+        ]
+    }
+}
+
+fn pair(key: &str, value: Term) -> Term {
+    Term::Tuple(vec![Term::binary(key), value])
+}
+
+fn invalid(field: &str) -> Error {
+    Error::InvalidMetadata(format!("unexpected value for {}", field))
+}
+
+/// A list of `{Key, Value}` tuples or a map, where each key is a binary.
+fn proplist(term: &Term) -> Option<Vec<(&str, &Term)>> {
+    match term {
+        Term::Map(pairs) => pairs
+            .iter()
+            .map(|(key, value)| Some((key.as_binary_str()?, value)))
+            .collect(),
+        Term::List(elements) => elements
+            .iter()
+            .map(|element| match element.as_tuple()? {
+                [key, value] => Some((key.as_binary_str()?, value)),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+/// Requirements are written as `{Name, Properties}` pairs by current versions
+/// of Hex, and as a list of properties including the `name` by older versions.
+fn requirements(term: &Term) -> Option<Result<HashMap<String, Dependency>, Error>> {
+    let requirements = match proplist(term) {
+        Some(requirements) => requirements,
+        None => term
+            .as_list()?
+            .iter()
+            .map(|properties| {
+                let name = proplist(properties)?
+                    .into_iter()
+                    .find(|(key, _)| *key == "name")?
+                    .1
+                    .as_binary_str()?;
+                Some((name, properties))
+            })
+            .collect::<Option<Vec<_>>>()?,
+    };
+
+    let mut dependencies = HashMap::new();
+    for (name, properties) in requirements {
+        let mut requirement = None;
+        let mut dependency_app = None;
+        let mut optional = false;
+        let mut repository = None;
+        for (key, value) in proplist(properties)? {
+            match key {
+                "requirement" => requirement = Some(value.as_binary_str()?),
+                "app" => dependency_app = Some(value.as_binary_str()?.to_string()),
+                "optional" => optional = value.as_bool()?,
+                "repository" => repository = Some(value.as_binary_str()?.to_string()),
+                _ => (),
+            }
+        }
+        let requirement = match Range::new(requirement?.to_string()) {
+            Ok(requirement) => requirement,
+            Err(e) => return Some(Err(Error::InvalidMetadata(e.to_string()))),
+        };
+        let dependency = Dependency {
+            requirement,
+            optional,
+            app: dependency_app,
+            repository,
+        };
+        let _ = dependencies.insert(name.to_string(), dependency);
+    }
+    Some(Ok(dependencies))
+}
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+
+use super::*;
+use crate::Dependency;
+use crate::version::{Range, Version};
+
+fn entries(data: &[u8]) -> Vec<(String, Vec<u8>)> {
+    tar::Archive::new(data)
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().to_string();
+            let mut contents = Vec::new();
+            let _ = entry.read_to_end(&mut contents).unwrap();
+            (path, contents)
+        })
+        .collect()
+}
+
+fn example_metadata() -> PackageMetadata {
+    let mut metadata = PackageMetadata::new("my_package", Version::new(1, 2, 3));
+    metadata.description = "A \"quoted\" package".into();
+    metadata.licenses = vec!["Apache-2.0".into()];
+    metadata.links = vec![(
+        "Repository".into(),
+        "https://github.com/gleam-lang/my_package".into(),
+    )];
+    metadata.build_tools = vec!["gleam".into()];
+    metadata.requirements = [
+        (
+            "gleam_stdlib".into(),
+            Dependency {
+                requirement: Range::new(">= 0.14.0 and < 2.0.0".into()).unwrap(),
+                optional: false,
+                app: None,
+                repository: None,
+            },
+        ),
+        (
+            "gleam_json".into(),
+            Dependency {
+                requirement: Range::new("~> 1.0".into()).unwrap(),
+                optional: true,
+                app: Some("json".into()),
+                repository: Some("hexpm".into()),
+            },
+        ),
+    ]
+    .into();
+    metadata
+}
+
+fn example_tarball() -> Tarball {
+    TarballBuilder::new(example_metadata())
+        .add_file("src/my_package.gleam", "pub fn main() { Nil }")
+        .add_file("gleam.toml", "name = \"my_package\"")
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn outer_tarball_entries() {
+    let tarball = example_tarball();
+    let entries = entries(&tarball.data);
+    let names = entries.iter().map(|(p, _)| p.as_str()).collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        ["VERSION", "CHECKSUM", "metadata.config", "contents.tar.gz"]
+    );
+    assert_eq!(entries[0].1, b"3");
+}
+
+#[test]
+fn metadata_config() {
+    let tarball = example_tarball();
+    let entries = entries(&tarball.data);
+    assert_eq!(
+        std::str::from_utf8(&entries[2].1).unwrap(),
+        r#"{<<"app">>,<<"my_package">>}.
+{<<"build_tools">>,[<<"gleam">>]}.
+{<<"description">>,<<"A \"quoted\" package">>}.
+{<<"files">>,[<<"gleam.toml">>,<<"src/my_package.gleam">>]}.
+{<<"licenses">>,[<<"Apache-2.0">>]}.
+{<<"links">>,[{<<"Repository">>,<<"https://github.com/gleam-lang/my_package">>}]}.
+{<<"name">>,<<"my_package">>}.
+{<<"requirements">>,[{<<"gleam_json">>,[{<<"app">>,<<"json">>},{<<"optional">>,true},{<<"requirement">>,<<"~> 1.0">>},{<<"repository">>,<<"hexpm">>}]},{<<"gleam_stdlib">>,[{<<"app">>,<<"gleam_stdlib">>},{<<"optional">>,false},{<<"requirement">>,<<">= 0.14.0 and < 2.0.0">>}]}]}.
+{<<"version">>,<<"1.2.3">>}.
+"#
+    );
+}
+
+#[test]
+fn contents_tarball() {
+    let tarball = example_tarball();
+    let entries = entries(&tarball.data);
+    let mut contents = Vec::new();
+    let _ = GzDecoder::new(entries[3].1.as_slice())
+        .read_to_end(&mut contents)
+        .unwrap();
+    assert_eq!(
+        self::entries(&contents),
+        [
+            ("gleam.toml".into(), b"name = \"my_package\"".to_vec()),
+            (
+                "src/my_package.gleam".into(),
+                b"pub fn main() { Nil }".to_vec()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn checksums() {
+    let tarball = example_tarball();
+    let entries = entries(&tarball.data);
+
+    let mut inner = Vec::new();
+    inner.extend_from_slice(&entries[0].1);
+    inner.extend_from_slice(&entries[2].1);
+    inner.extend_from_slice(&entries[3].1);
+    let inner_checksum = digest(&SHA256, &inner).as_ref().to_vec();
+
+    assert_eq!(tarball.inner_checksum, inner_checksum);
+    assert_eq!(
+        entries[1].1,
+        base16::encode_upper(&inner_checksum).into_bytes()
+    );
+    assert_eq!(
+        tarball.outer_checksum,
+        digest(&SHA256, &tarball.data).as_ref().to_vec()
+    );
+}
+
+#[test]
+fn build_is_deterministic() {
+    assert_eq!(example_tarball(), example_tarball());
+}
+
+#[test]
+fn invalid_package_name() {
+    let metadata = PackageMetadata::new("Not valid", Version::new(1, 0, 0));
+    match TarballBuilder::new(metadata).build() {
+        Err(Error::InvalidPackageNameFormat(name)) if name == "Not valid" => (),
+        result => panic!("expected InvalidPackageNameFormat, got {:?}", result),
+    }
+}
+
+#[test]
+fn invalid_file_paths() {
+    for path in [
+        "",
+        "/etc/passwd",
+        "../secret",
+        "src/../../secret",
+        "./src",
+        "src\\main",
+    ] {
+        let result = TarballBuilder::new(example_metadata())
+            .add_file(path, "")
+            .build();
+        match result {
+            Err(Error::InvalidPath(p)) if p == path => (),
+            result => panic!("expected InvalidPath for {:?}, got {:?}", path, result),
+        }
+    }
+}
+
+fn gleam_stdlib_archive() -> ReleaseArchive {
+    ReleaseArchive::parse(std::include_bytes!("../../test/gleam_stdlib-0.14.0.tar")).unwrap()
+}
+
+/// Wrap a gzipped contents tarball in an outer tarball with a valid checksum.
+fn outer_tarball(contents: &[u8]) -> Vec<u8> {
+    outer_tarball_with_metadata(&example_metadata().to_config(), contents)
+}
+
+fn outer_tarball_with_metadata(metadata: &str, contents: &[u8]) -> Vec<u8> {
+    let mut inner = Vec::new();
+    inner.extend_from_slice(TARBALL_VERSION.as_bytes());
+    inner.extend_from_slice(metadata.as_bytes());
+    inner.extend_from_slice(contents);
+    let checksum = base16::encode_upper(digest(&SHA256, &inner).as_ref());
+
+    let mut tarball = tar::Builder::new(Vec::new());
+    append_entry(&mut tarball, "VERSION", TARBALL_VERSION.as_bytes()).unwrap();
+    append_entry(&mut tarball, "CHECKSUM", checksum.as_bytes()).unwrap();
+    append_entry(&mut tarball, "metadata.config", metadata.as_bytes()).unwrap();
+    append_entry(&mut tarball, "contents.tar.gz", contents).unwrap();
+    tarball.into_inner().unwrap()
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[test]
+fn parse_gleam_stdlib_metadata() {
+    let mut archive = gleam_stdlib_archive();
+    assert_eq!(archive.tarball_version, "3");
+    let files = std::mem::take(&mut archive.metadata.files);
+    assert_eq!(files.len(), 89);
+    assert_eq!(files[0], "CHANGELOG.md");
+    assert_eq!(
+        archive.metadata,
+        PackageMetadata {
+            name: "gleam_stdlib".into(),
+            version: Version::new(0, 14, 0),
+            app: Some("gleam_stdlib".into()),
+            description: "A standard library for the Gleam programming language".into(),
+            licenses: vec!["Apache 2.0".into()],
+            links: vec![(
+                "GitHub".into(),
+                "https://github.com/gleam-lang/stdlib".into()
+            )],
+            requirements: HashMap::new(),
+            build_tools: vec!["rebar3".into()],
+            files: vec![],
+        }
+    );
+}
+
+#[test]
+fn parse_gleam_stdlib_checksums() {
+    let archive = gleam_stdlib_archive();
+    assert_eq!(
+        base16::encode_upper(&archive.inner_checksum),
+        "765D90AC06F97D7A8E8F7AC4BEDA373879D98C78A27FD8DEACC98AA34DA2DDCE"
+    );
+    assert_eq!(
+        base16::encode_lower(&archive.outer_checksum),
+        "9107f6a859cb96945ad9a099085db028ca2bebb3c8ea42eec227b51c614cc2e0"
+    );
+}
+
+#[test]
+fn parse_gleam_stdlib_contents() {
+    let archive = gleam_stdlib_archive();
+    assert_eq!(archive.contents().count(), 89);
+    let readme = archive
+        .contents()
+        .find(|entry| entry.path == "README.md")
+        .unwrap();
+    assert!(readme.contents.starts_with(b"# stdlib"));
+}
+
+#[test]
+fn parse_built_tarball() {
+    let archive = ReleaseArchive::parse(&example_tarball().data).unwrap();
+    // Application names default to the package name when written
+    let mut expected = example_metadata();
+    expected.files = vec!["gleam.toml".into(), "src/my_package.gleam".into()];
+    expected.app = Some("my_package".into());
+    expected.requirements.get_mut("gleam_stdlib").unwrap().app = Some("gleam_stdlib".into());
+    assert_eq!(archive.metadata, expected);
+    assert_eq!(
+        archive
+            .contents()
+            .map(|e| e.path.as_str())
+            .collect::<Vec<_>>(),
+        ["gleam.toml", "src/my_package.gleam"]
+    );
+}
+
+#[test]
+fn parse_old_style_requirements() {
+    let config = r#"{<<"name">>,<<"old">>}.
+{<<"version">>,<<"1.0.0">>}.
+{<<"requirements">>,[[{<<"name">>,<<"plug">>},{<<"app">>,<<"plug">>},{<<"optional">>,true},{<<"requirement">>,<<"~> 1.0">>}]]}.
+"#;
+    let metadata = PackageMetadata::from_config(config).unwrap();
+    let plug = metadata.requirements.get("plug").unwrap();
+    assert_eq!(plug.requirement.as_str(), "~> 1.0");
+    assert!(plug.optional);
+}
+
+#[test]
+fn parse_rejects_invalid_package_name() {
+    let metadata = r#"{<<"name">>,<<"../../x">>}.
+{<<"version">>,<<"1.0.0">>}.
+"#;
+    let contents = tar::Builder::new(Vec::new()).into_inner().unwrap();
+    let data = outer_tarball_with_metadata(metadata, &gzip(&contents));
+    match ReleaseArchive::parse(&data) {
+        Err(Error::InvalidPackageNameFormat(name)) if name == "../../x" => (),
+        result => panic!("expected InvalidPackageNameFormat, got {:?}", result),
+    }
+}
+
+#[test]
+fn verify_against_release() {
+    let archive = gleam_stdlib_archive();
+    let mut release = Release {
+        version: Version::new(0, 14, 0),
+        requirements: HashMap::new(),
+        retirement_status: None,
+        inner_checksum: archive.inner_checksum.clone(),
+        outer_checksum: archive.outer_checksum.clone(),
+        meta: (),
+    };
+    archive.verify(&release).unwrap();
+
+    release.inner_checksum = vec![];
+    archive.verify(&release).unwrap();
+
+    release.outer_checksum = vec![1, 2, 3];
+    match archive.verify(&release) {
+        Err(Error::IncorrectChecksum) => (),
+        result => panic!("expected IncorrectChecksum, got {:?}", result),
+    }
+}
+
+#[test]
+fn parse_incorrect_checksum() {
+    let mut data = std::include_bytes!("../../test/gleam_stdlib-0.14.0.tar").to_vec();
+    // The CHECKSUM file contents begin in the fourth 512 byte block
+    data[1536] = b'0';
+    match ReleaseArchive::parse(&data) {
+        Err(Error::IncorrectChecksum) => (),
+        result => panic!("expected IncorrectChecksum, got {:?}", result),
+    }
+}
+
+#[test]
+fn parse_missing_file() {
+    let mut tarball = tar::Builder::new(Vec::new());
+    append_entry(&mut tarball, "VERSION", b"3").unwrap();
+    let data = tarball.into_inner().unwrap();
+    match ReleaseArchive::parse(&data) {
+        Err(Error::MissingFile("CHECKSUM")) => (),
+        result => panic!("expected MissingFile, got {:?}", result),
+    }
+}
+
+#[test]
+fn parse_rejects_symlinks() {
+    let mut contents = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_ustar();
+    header.set_entry_type(tar::EntryType::Symlink);
+    header.set_size(0);
+    contents
+        .append_link(&mut header, "link", "/etc/passwd")
+        .unwrap();
+    let data = outer_tarball(&gzip(&contents.into_inner().unwrap()));
+    match ReleaseArchive::parse(&data) {
+        Err(Error::UnsupportedEntry(path)) if path == "link" => (),
+        result => panic!("expected UnsupportedEntry, got {:?}", result),
+    }
+}
+
+#[test]
+fn parse_rejects_path_traversal() {
+    let mut header = tar::Header::new_old();
+    header.as_old_mut().name[..9].copy_from_slice(b"../secret");
+    header.set_size(0);
+    header.set_entry_type(tar::EntryType::Regular);
+    header.set_cksum();
+    let mut contents = tar::Builder::new(Vec::new());
+    contents.append(&header, std::io::empty()).unwrap();
+    let data = outer_tarball(&gzip(&contents.into_inner().unwrap()));
+    match ReleaseArchive::parse(&data) {
+        Err(Error::InvalidPath(path)) if path == "../secret" => (),
+        result => panic!("expected InvalidPath, got {:?}", result),
+    }
+}